@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use std::fs::{create_dir_all, read_to_string, write};
 use std::path::Path;
 use std::sync::LazyLock;
@@ -6,11 +8,22 @@ use heck::ToShoutySnakeCase;
 use regex::Regex;
 
 static CMD_ID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\sCmd\w*\s=\s\d+"#).unwrap());
+static MESSAGE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^message\s+(\w+)\s*\{"#).unwrap());
+static PACKAGE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^package\s+([\w.]+)\s*;"#).unwrap());
+// both are varints on the wire, which is all `retcode_tag_for` callers encode
+static RETCODE_FIELD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s+(?:uint32|Retcode)\s+retcode\s*=\s*(\d+)\s*;"#).unwrap());
 
 const OUTPUT_DIR: &str = "include/proto/";
 const CMD_ID_OUTPUT_FILE: &str = "./include/proto/cmd.rs";
 const PROTO_FILE: &str = "StarRail.proto";
 
+const REQ_SUFFIX: &str = "CsReq";
+const RSP_SUFFIX: &str = "ScRsp";
+const NOTIFY_SUFFIX: &str = "ScNotify";
+
 fn main() -> std::io::Result<()> {
     if !Path::new(OUTPUT_DIR).exists() {
         create_dir_all(OUTPUT_DIR)?;
@@ -23,10 +36,20 @@ fn main() -> std::io::Result<()> {
             .compile_protos(&[PROTO_FILE], &["."])
             .unwrap();
 
-        let cmd_ids = parse_cmd_ids(PROTO_FILE)?;
-        let cmd_ids = cmd_ids.join("\n");
+        let file_content = read_to_string(PROTO_FILE)?;
+        let cmd_ids = parse_cmd_ids(&file_content);
+        let messages = parse_message_names(&file_content);
+        let retcode_tags = parse_retcode_tags(&file_content);
+
+        let mut output = String::with_capacity(256 * 1024);
+        write_cmd_consts(&mut output, &cmd_ids);
+        write_type_names(&mut output, &cmd_ids, &messages);
+        write_cmd_name_fn(&mut output, &cmd_ids);
+        write_response_cmd_fn(&mut output, &cmd_ids);
+        write_cmd_kind_fn(&mut output, &cmd_ids);
+        write_retcode_tag_fn(&mut output, &cmd_ids, &retcode_tags);
 
-        write(CMD_ID_OUTPUT_FILE, &cmd_ids)?;
+        write(CMD_ID_OUTPUT_FILE, &output)?;
         println!("cargo::rerun-if-changed={}", PROTO_FILE);
 
         Ok(())
@@ -35,22 +58,194 @@ fn main() -> std::io::Result<()> {
     }
 }
 
-fn parse_cmd_ids(file_path: &str) -> std::io::Result<Vec<String>> {
-    let file_content = read_to_string(file_path)?;
+// (message name, cmd id), in the order they appear in the proto file.
+fn parse_cmd_ids(file_content: &str) -> Vec<(String, u16)> {
     let mut result = Vec::with_capacity(2049);
 
-    for cap in CMD_ID_REGEX.captures_iter(&file_content) {
+    for cap in CMD_ID_REGEX.captures_iter(file_content) {
         if let Some(cmd_line) = cap.get(0) {
             let cmd_line = cmd_line.as_str();
             let cmd_line = cmd_line.replace("\tCmd", "");
             let cmd_parts = cmd_line.split(" = ").collect::<Vec<&str>>();
             assert_eq!(cmd_parts.len(), 2);
-            let cmd_name = cmd_parts[0].to_shouty_snake_case();
+            let cmd_name = cmd_parts[0].to_string();
             let cmd_id = cmd_parts[1].parse::<u16>().unwrap();
-            let cmd_const = format!("pub const {}: u16 = {};", cmd_name, cmd_id);
-            result.push(cmd_const)
+            result.push((cmd_name, cmd_id))
+        }
+    }
+
+    result
+}
+
+// message name -> fully-qualified prost type name
+fn parse_message_names(file_content: &str) -> HashMap<String, String> {
+    let package = PACKAGE_REGEX
+        .captures(file_content)
+        .and_then(|cap| cap.get(1))
+        .map(|v| v.as_str());
+
+    MESSAGE_REGEX
+        .captures_iter(file_content)
+        .filter_map(|cap| cap.get(1))
+        .map(|v| {
+            let name = v.as_str().to_string();
+            let full_name = match package {
+                Some(package) => format!("{}.{}", package, name),
+                None => name.clone(),
+            };
+            (name, full_name)
+        })
+        .collect()
+}
+
+// message name -> field number of its top-level `retcode`
+fn parse_retcode_tags(file_content: &str) -> HashMap<String, u32> {
+    let mut result = HashMap::new();
+    let mut current = None;
+
+    for line in file_content.lines() {
+        if let Some(cap) = MESSAGE_REGEX.captures(line) {
+            current = cap.get(1).map(|v| v.as_str().to_string());
+        } else if let (Some(message), Some(cap)) = (&current, RETCODE_FIELD_REGEX.captures(line)) {
+            result.insert(message.clone(), cap[1].parse::<u32>().unwrap());
+        }
+    }
+
+    result
+}
+
+fn write_cmd_consts(output: &mut String, cmd_ids: &[(String, u16)]) {
+    for (cmd_name, cmd_id) in cmd_ids {
+        writeln!(
+            output,
+            "pub const {}: u16 = {};",
+            cmd_name.to_shouty_snake_case(),
+            cmd_id
+        )
+        .unwrap();
+    }
+}
+
+fn write_type_names(
+    output: &mut String,
+    cmd_ids: &[(String, u16)],
+    messages: &HashMap<String, String>,
+) {
+    output.push_str("\npub mod type_name {\n");
+    for (cmd_name, _) in cmd_ids {
+        if let Some(full_name) = messages.get(cmd_name) {
+            writeln!(
+                output,
+                "    pub const {}: &str = \"{}\";",
+                cmd_name.to_shouty_snake_case(),
+                full_name
+            )
+            .unwrap();
         }
     }
+    output.push_str("}\n");
+}
+
+fn write_cmd_name_fn(output: &mut String, cmd_ids: &[(String, u16)]) {
+    // the `*TypeNone = 0` entries are enum placeholders, not packets
+    let mut seen = HashSet::from([0]);
+
+    output.push_str("\npub fn cmd_name(cmd: u16) -> Option<&'static str> {\n");
+    output.push_str("    match cmd {\n");
+    for (cmd_name, cmd_id) in cmd_ids {
+        if seen.insert(*cmd_id) {
+            writeln!(output, "        {} => Some(\"{}\"),", cmd_id, cmd_name).unwrap();
+        }
+    }
+    output.push_str("        _ => None,\n");
+    output.push_str("    }\n");
+    output.push_str("}\n");
+}
+
+fn write_response_cmd_fn(output: &mut String, cmd_ids: &[(String, u16)]) {
+    let names = cmd_ids
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<HashSet<&str>>();
+
+    output.push_str("\npub fn response_cmd_for(req: u16) -> Option<u16> {\n");
+    output.push_str("    match req {\n");
+    for (cmd_name, _) in cmd_ids {
+        let Some(base) = cmd_name.strip_suffix(REQ_SUFFIX) else {
+            continue;
+        };
+        let rsp_name = format!("{}{}", base, RSP_SUFFIX);
+        if names.contains(rsp_name.as_str()) {
+            writeln!(
+                output,
+                "        {} => Some({}),",
+                cmd_name.to_shouty_snake_case(),
+                rsp_name.to_shouty_snake_case()
+            )
+            .unwrap();
+        }
+    }
+    output.push_str("        _ => None,\n");
+    output.push_str("    }\n");
+    output.push_str("}\n");
+}
+
+fn write_cmd_kind_fn(output: &mut String, cmd_ids: &[(String, u16)]) {
+    output.push_str("\n#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n");
+    output.push_str("pub enum CmdKind {\n");
+    output.push_str("    Request,\n");
+    output.push_str("    Response,\n");
+    output.push_str("    Notify,\n");
+    output.push_str("}\n");
+
+    output.push_str("\npub fn cmd_kind(cmd: u16) -> Option<CmdKind> {\n");
+    output.push_str("    match cmd {\n");
+    for (cmd_name, _) in cmd_ids {
+        let kind = if cmd_name.ends_with(REQ_SUFFIX) {
+            "Request"
+        } else if cmd_name.ends_with(RSP_SUFFIX) {
+            "Response"
+        } else if cmd_name.ends_with(NOTIFY_SUFFIX) {
+            "Notify"
+        } else {
+            continue;
+        };
+        writeln!(
+            output,
+            "        {} => Some(CmdKind::{}),",
+            cmd_name.to_shouty_snake_case(),
+            kind
+        )
+        .unwrap();
+    }
+    output.push_str("        _ => None,\n");
+    output.push_str("    }\n");
+    output.push_str("}\n");
+}
 
-    Ok(result)
+// lets a response carrying only a failing retcode be built without knowing its type
+fn write_retcode_tag_fn(
+    output: &mut String,
+    cmd_ids: &[(String, u16)],
+    retcode_tags: &HashMap<String, u32>,
+) {
+    output.push_str("\npub fn retcode_tag_for(rsp: u16) -> Option<u32> {\n");
+    output.push_str("    match rsp {\n");
+    for (cmd_name, _) in cmd_ids {
+        if !cmd_name.ends_with(RSP_SUFFIX) {
+            continue;
+        }
+        if let Some(tag) = retcode_tags.get(cmd_name) {
+            writeln!(
+                output,
+                "        {} => Some({}),",
+                cmd_name.to_shouty_snake_case(),
+                tag
+            )
+            .unwrap();
+        }
+    }
+    output.push_str("        _ => None,\n");
+    output.push_str("    }\n");
+    output.push_str("}\n");
 }
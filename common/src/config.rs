@@ -0,0 +1,830 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{read_to_string, write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const DEFAULT_CONFIG_PATH: &str = "./config.json";
+const CONFIG_PATH_ENV: &str = "RAILGUN_CONFIG";
+const ENV_PREFIX: &str = "RAILGUN";
+
+static CONFIG: OnceLock<ServerConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub dns: String,
+    pub dispatch: DispatchConfig,
+    pub gameserver: GameserverConfig,
+    pub certs: CertsConfig,
+    pub database: DatabaseConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DispatchConfig {
+    pub bind_port: u16,
+    // a stored hotfix older than this is fetched again, 0 fetches on every query
+    pub hotfix_ttl_minutes: u64,
+    // only serve stored hotfixes and never ask upstream, for running with no network
+    pub hotfix_offline: bool,
+    // where hotfixes are fetched from, the first whose prefix the version starts with
+    pub hotfix_upstreams: Vec<HotfixUpstreamConfig>,
+    pub regions: Vec<RegionConfig>,
+    // off lets anyone who knows a username log in as them, which is all a client
+    // not patched with `certs.login_public_key_file` can do. on needs that patch
+    pub verify_password: bool,
+    pub rate_limits: Vec<RateLimitConfig>,
+    // sent as `Authorization: Bearer <key>` to `/admin`, empty turns the admin api off
+    pub admin_api_key: String,
+}
+
+// `gateway_url` is what the client calls for this region's `query_gateway`,
+// dispatch adds `region=<name>` to it. left empty (or 0), `gateway_url`,
+// `gameserver_ip` and `gameserver_port` follow `host` and the bind ports
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegionConfig {
+    pub name: String,
+    pub title: String,
+    pub env_type: String,
+    pub gateway_url: String,
+    pub gameserver_ip: String,
+    pub gameserver_port: u16,
+}
+
+// `url` is the official dispatch for versions starting with `version_prefix`.
+// with a `proxy_url` it's asked as `<proxy_url>/<url's host>/query_gateway`,
+// empty asks `url` directly. a request is tried `retries` more times after it fails
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HotfixUpstreamConfig {
+    pub version_prefix: String,
+    pub url: String,
+    pub proxy_url: String,
+    pub timeout_seconds: u64,
+    pub retries: u32,
+}
+
+// token bucket limits for one route. `route` is the pattern its handler is
+// registered with, e.g. `/{product}/mdk/shield/api/login`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub route: String,
+    pub per_ip: Option<BucketConfig>,
+    // keyed on the `account_field` of the json body, e.g. the username
+    pub per_account: Option<BucketConfig>,
+    pub account_field: String,
+    // answer with a retcode the sdk shows instead of a bare 429
+    pub sdk_response: bool,
+}
+
+// `burst` requests can be made at once, then `per_minute` more each minute
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameserverConfig {
+    pub bind_port: u16,
+    // off sends packets in the clear, which only clients patched to skip the
+    // xor work with. on needs `certs.dispatch_key_file` and `certs.dispatch_seed_file`
+    pub xor_enabled: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CertsConfig {
+    pub dir: String,
+    pub crt_file: String,
+    pub key_file: String,
+    // rsa key pair the client encrypts its login password with. the public
+    // key is only written out for patching the client, dispatch never reads it
+    pub login_private_key_file: String,
+    pub login_public_key_file: String,
+    // what session tokens are signed with, generated on first run. dispatch and
+    // the gameserver have to share it
+    pub token_secret_file: String,
+    // the 4096 byte xor key clients use until the session key is handed out,
+    // and the ec2b blob they derive it from, which dispatch sends them
+    pub dispatch_key_file: String,
+    pub dispatch_seed_file: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
+    pub mongodb_uri: String,
+    pub sqlite_path: String,
+    // report pending migrations and exit instead of starting
+    pub migrations_dry_run: bool,
+}
+
+// `sqlite` is a file next to the binaries that dispatch and the gameserver share.
+// `memory` keeps everything in process and loses it on restart. dispatch and the
+// gameserver each get their own, so it's only good for trying things out and tests
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    Mongodb,
+    Sqlite,
+    Memory,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: String::from("127.0.0.1"),
+            dns: String::from("localhost"),
+            dispatch: DispatchConfig::default(),
+            gameserver: GameserverConfig::default(),
+            certs: CertsConfig::default(),
+            database: DatabaseConfig::default(),
+        }
+    }
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            bind_port: 21000,
+            hotfix_ttl_minutes: 60,
+            hotfix_offline: false,
+            hotfix_upstreams: [
+                ("CNPROD", "https://prod-gf-cn-dp01.bhsr.com"),
+                ("CNBETA", "https://beta-release01-cn.bhsr.com"),
+                ("OSPROD", "https://prod-official-asia-dp01.starrails.com"),
+                ("OSBETA", "https://beta-release01-asia.starrails.com"),
+            ]
+            .into_iter()
+            .map(|(version_prefix, url)| HotfixUpstreamConfig {
+                version_prefix: String::from(version_prefix),
+                url: String::from(url),
+                proxy_url: String::from("https://proxy1.neonteam.dev"),
+                ..Default::default()
+            })
+            .collect(),
+            regions: vec![RegionConfig::default()],
+            verify_password: false,
+            rate_limits: vec![
+                RateLimitConfig {
+                    route: String::from("/account/register"),
+                    per_ip: Some(BucketConfig {
+                        burst: 5,
+                        per_minute: 2,
+                    }),
+                    per_account: None,
+                    account_field: String::from("username"),
+                    sdk_response: false,
+                },
+                RateLimitConfig {
+                    route: String::from("/{product}/mdk/shield/api/login"),
+                    per_ip: Some(BucketConfig::default()),
+                    per_account: Some(BucketConfig {
+                        burst: 5,
+                        per_minute: 2,
+                    }),
+                    account_field: String::from("account"),
+                    sdk_response: true,
+                },
+                RateLimitConfig {
+                    route: String::from("/{product}/mdk/shield/api/verify"),
+                    per_ip: Some(BucketConfig::default()),
+                    per_account: None,
+                    account_field: String::from("uid"),
+                    sdk_response: true,
+                },
+                RateLimitConfig {
+                    route: String::from("/account/risky/api/check"),
+                    per_ip: Some(BucketConfig::default()),
+                    per_account: Some(BucketConfig {
+                        burst: 10,
+                        per_minute: 5,
+                    }),
+                    account_field: String::from("username"),
+                    sdk_response: true,
+                },
+            ],
+            admin_api_key: String::new(),
+        }
+    }
+}
+
+impl Default for RegionConfig {
+    fn default() -> Self {
+        Self {
+            name: String::from("Railgun"),
+            title: String::from("Railgun"),
+            env_type: String::from("2"),
+            gateway_url: String::new(),
+            gameserver_ip: String::new(),
+            gameserver_port: 0,
+        }
+    }
+}
+
+// only the connection settings have a sensible default, an entry still has to
+// say which versions and url it's for
+impl Default for HotfixUpstreamConfig {
+    fn default() -> Self {
+        Self {
+            version_prefix: String::new(),
+            url: String::new(),
+            proxy_url: String::new(),
+            timeout_seconds: 10,
+            retries: 2,
+        }
+    }
+}
+
+impl Default for BucketConfig {
+    fn default() -> Self {
+        Self {
+            burst: 20,
+            per_minute: 10,
+        }
+    }
+}
+
+impl Default for GameserverConfig {
+    fn default() -> Self {
+        Self {
+            bind_port: 23301,
+            xor_enabled: false,
+        }
+    }
+}
+
+impl Default for CertsConfig {
+    fn default() -> Self {
+        Self {
+            dir: String::from("./certs"),
+            crt_file: String::from("./certs/default.crt"),
+            key_file: String::from("./certs/default.key"),
+            login_private_key_file: String::from("./certs/login_private.pem"),
+            login_public_key_file: String::from("./certs/login_public.pem"),
+            token_secret_file: String::from("./certs/token_secret"),
+            dispatch_key_file: String::from("./certs/dispatch_key.bin"),
+            dispatch_seed_file: String::from("./certs/dispatch_seed.bin"),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: DatabaseBackend::Mongodb,
+            mongodb_uri: String::from("mongodb://localhost:27017"),
+            sqlite_path: String::from("./railgun.db"),
+            migrations_dry_run: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn dispatch_bind_target(&self) -> (&str, u16) {
+        (&self.host, self.dispatch.bind_port)
+    }
+
+    pub fn gameserver_bind_target(&self) -> (&str, u16) {
+        (&self.host, self.gameserver.bind_port)
+    }
+
+    // no name, or one that isn't listed, falls back to the first region
+    pub fn region(&self, name: Option<&str>) -> &RegionConfig {
+        let Some(name) = name else {
+            return &self.dispatch.regions[0];
+        };
+        match self.dispatch.regions.iter().find(|v| v.name == name) {
+            Some(v) => v,
+            None => {
+                tracing::warn!(
+                    "Unknown region {}, using {}",
+                    name,
+                    self.dispatch.regions[0].name
+                );
+                &self.dispatch.regions[0]
+            }
+        }
+    }
+
+    fn fill_regions(&mut self) {
+        for region in &mut self.dispatch.regions {
+            if region.gateway_url.is_empty() {
+                region.gateway_url = format!(
+                    "https://{}:{}/query_gateway",
+                    self.host, self.dispatch.bind_port
+                );
+            }
+            if region.gameserver_ip.is_empty() {
+                region.gameserver_ip = self.host.clone();
+            }
+            if region.gameserver_port == 0 {
+                region.gameserver_port = self.gameserver.bind_port;
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.host.parse::<IpAddr>().is_err() {
+            return Err(ConfigError::invalid("host", "not an ip address"));
+        }
+        if self.dns.is_empty() {
+            return Err(ConfigError::invalid("dns", "empty"));
+        }
+        if self.dispatch.bind_port == 0 {
+            return Err(ConfigError::invalid("dispatch.bind_port", "must not be 0"));
+        }
+        if self.dispatch.regions.is_empty() {
+            return Err(ConfigError::invalid("dispatch.regions", "empty"));
+        }
+        let mut region_names = HashSet::new();
+        for (i, region) in self.dispatch.regions.iter().enumerate() {
+            region.validate(&format!("dispatch.regions[{}]", i))?;
+            if !region_names.insert(region.name.as_str()) {
+                return Err(ConfigError::invalid(
+                    &format!("dispatch.regions[{}].name", i),
+                    "duplicate region name",
+                ));
+            }
+        }
+        if self.dispatch.verify_password && self.certs.login_private_key_file.is_empty() {
+            return Err(ConfigError::invalid(
+                "certs.login_private_key_file",
+                "empty while dispatch.verify_password is on",
+            ));
+        }
+        if self.certs.token_secret_file.is_empty() {
+            return Err(ConfigError::invalid("certs.token_secret_file", "empty"));
+        }
+        let admin_api_key_len = self.dispatch.admin_api_key.len();
+        if admin_api_key_len > 0 && admin_api_key_len < 16 {
+            return Err(ConfigError::invalid(
+                "dispatch.admin_api_key",
+                "shorter than 16 characters",
+            ));
+        }
+        for (i, upstream) in self.dispatch.hotfix_upstreams.iter().enumerate() {
+            upstream.validate(&format!("dispatch.hotfix_upstreams[{}]", i))?;
+        }
+        let mut rate_limit_routes = HashSet::new();
+        for (i, rate_limit) in self.dispatch.rate_limits.iter().enumerate() {
+            rate_limit.validate(&format!("dispatch.rate_limits[{}]", i))?;
+            if !rate_limit_routes.insert(rate_limit.route.as_str()) {
+                return Err(ConfigError::invalid(
+                    &format!("dispatch.rate_limits[{}].route", i),
+                    "duplicate route",
+                ));
+            }
+        }
+        if self.gameserver.bind_port == 0 {
+            return Err(ConfigError::invalid(
+                "gameserver.bind_port",
+                "must not be 0",
+            ));
+        }
+        if self.gameserver.xor_enabled {
+            if self.certs.dispatch_key_file.is_empty() {
+                return Err(ConfigError::invalid(
+                    "certs.dispatch_key_file",
+                    "empty while gameserver.xor_enabled is on",
+                ));
+            }
+            if self.certs.dispatch_seed_file.is_empty() {
+                return Err(ConfigError::invalid(
+                    "certs.dispatch_seed_file",
+                    "empty while gameserver.xor_enabled is on",
+                ));
+            }
+        }
+        if self.gameserver.bind_port == self.dispatch.bind_port {
+            return Err(ConfigError::invalid(
+                "gameserver.bind_port",
+                "same as dispatch.bind_port",
+            ));
+        }
+        match self.database.backend {
+            DatabaseBackend::Mongodb if !self.database.mongodb_uri.starts_with("mongodb") => {
+                return Err(ConfigError::invalid(
+                    "database.mongodb_uri",
+                    "not a mongodb:// uri",
+                ));
+            }
+            DatabaseBackend::Sqlite if self.database.sqlite_path.is_empty() => {
+                return Err(ConfigError::invalid("database.sqlite_path", "empty"));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl RegionConfig {
+    pub fn dispatch_url(&self) -> String {
+        let separator = if self.gateway_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        format!("{}{}region={}", self.gateway_url, separator, self.name)
+    }
+
+    fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        if self.name.is_empty() {
+            return Err(ConfigError::invalid(&format!("{}.name", key), "empty"));
+        }
+        // it goes into the query string as is
+        if !self
+            .name
+            .chars()
+            .all(|v| v.is_ascii_alphanumeric() || matches!(v, '-' | '_' | '.'))
+        {
+            return Err(ConfigError::invalid(
+                &format!("{}.name", key),
+                "only letters, digits, '-', '_' and '.' are allowed",
+            ));
+        }
+        if self.env_type.parse::<u32>().is_err() {
+            return Err(ConfigError::invalid(
+                &format!("{}.env_type", key),
+                "not a number",
+            ));
+        }
+        if !self.gateway_url.starts_with("http") {
+            return Err(ConfigError::invalid(
+                &format!("{}.gateway_url", key),
+                "not an http(s) url",
+            ));
+        }
+        if self.gateway_url.contains("region=") {
+            return Err(ConfigError::invalid(
+                &format!("{}.gateway_url", key),
+                "has a region, dispatch adds it",
+            ));
+        }
+        if self.gameserver_ip.parse::<IpAddr>().is_err() {
+            return Err(ConfigError::invalid(
+                &format!("{}.gameserver_ip", key),
+                "not an ip address",
+            ));
+        }
+        if self.gameserver_port == 0 {
+            return Err(ConfigError::invalid(
+                &format!("{}.gameserver_port", key),
+                "must not be 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl HotfixUpstreamConfig {
+    fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        if self.version_prefix.is_empty() {
+            return Err(ConfigError::invalid(
+                &format!("{}.version_prefix", key),
+                "empty",
+            ));
+        }
+        if !self.url.starts_with("http") {
+            return Err(ConfigError::invalid(
+                &format!("{}.url", key),
+                "not an http(s) url",
+            ));
+        }
+        if !self.proxy_url.is_empty() && !self.proxy_url.starts_with("http") {
+            return Err(ConfigError::invalid(
+                &format!("{}.proxy_url", key),
+                "not an http(s) url",
+            ));
+        }
+        if self.timeout_seconds == 0 {
+            return Err(ConfigError::invalid(
+                &format!("{}.timeout_seconds", key),
+                "must not be 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl RateLimitConfig {
+    fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        if !self.route.starts_with('/') {
+            return Err(ConfigError::invalid(
+                &format!("{}.route", key),
+                "must start with /",
+            ));
+        }
+        if let Some(bucket) = &self.per_ip {
+            bucket.validate(&format!("{}.per_ip", key))?;
+        }
+        if let Some(bucket) = &self.per_account {
+            bucket.validate(&format!("{}.per_account", key))?;
+            if self.account_field.is_empty() {
+                return Err(ConfigError::invalid(
+                    &format!("{}.account_field", key),
+                    "empty while per_account is set",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BucketConfig {
+    fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        if self.burst == 0 {
+            return Err(ConfigError::invalid(
+                &format!("{}.burst", key),
+                "must not be 0",
+            ));
+        }
+        if self.per_minute == 0 {
+            return Err(ConfigError::invalid(
+                &format!("{}.per_minute", key),
+                "must not be 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    InvalidValue { key: String, reason: String },
+}
+
+impl ConfigError {
+    fn invalid(key: &str, reason: &str) -> Self {
+        Self::InvalidValue {
+            key: key.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) => write!(f, "{}", e),
+            Self::InvalidValue { key, reason } => write!(f, "`{}`: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+// Reads the config file (writing the defaults there on first run), then applies
+// `RAILGUN_<KEY>` env overrides, e.g. `RAILGUN_DISPATCH_BIND_PORT=8080`.
+// Keys missing from the file keep their defaults, including inside list entries,
+// so a file written by an older version still loads.
+pub fn init() -> Result<&'static ServerConfig, ConfigError> {
+    let path = std::env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+    let config = load(Path::new(&path))?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+pub fn get() -> &'static ServerConfig {
+    CONFIG
+        .get()
+        .expect("config::init must be called before config::get")
+}
+
+fn load(path: &Path) -> Result<ServerConfig, ConfigError> {
+    let default = serde_json::to_value(ServerConfig::default())?;
+
+    let mut value = default.clone();
+    if path.exists() {
+        let file_value = serde_json::from_str::<Value>(&read_to_string(path)?)?;
+        merge(&mut value, file_value);
+    } else {
+        write(path, serde_json::to_string_pretty(&default)?)?;
+        tracing::info!("wrote default config to {}", path.display());
+    }
+
+    apply_env_overrides(&mut value, &default, "", &|var| std::env::var(var).ok())?;
+
+    let mut config = deserialize(value)?;
+    config.fill_regions();
+    config.validate()?;
+
+    Ok(config)
+}
+
+fn deserialize(value: Value) -> Result<ServerConfig, ConfigError> {
+    serde_path_to_error::deserialize(value).map_err(|e| ConfigError::InvalidValue {
+        key: e.path().to_string(),
+        reason: e.into_inner().to_string(),
+    })
+}
+
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(v) => merge(v, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// walks the default config so only known keys can be overridden, and an
+// override is parsed as the type of the value it replaces
+fn apply_env_overrides(
+    value: &mut Value,
+    default: &Value,
+    key: &str,
+    get_env: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    if let (Value::Object(fields), Value::Object(default_fields)) = (&mut *value, default) {
+        for (name, default_field) in default_fields {
+            let field_key = match key {
+                "" => name.clone(),
+                _ => format!("{}.{}", key, name),
+            };
+            let field = fields.entry(name.clone()).or_insert(Value::Null);
+            apply_env_overrides(field, default_field, &field_key, get_env)?;
+        }
+        return Ok(());
+    }
+
+    let var = format!("{}_{}", ENV_PREFIX, key.replace('.', "_").to_uppercase());
+    let Some(raw) = get_env(&var) else {
+        return Ok(());
+    };
+
+    *value = match default {
+        Value::String(_) => Value::String(raw),
+        Value::Bool(_) => raw
+            .parse::<bool>()
+            .map(Value::Bool)
+            .map_err(|_| ConfigError::invalid(key, &format!("{} is not a bool", var)))?,
+        Value::Number(_) => raw
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| ConfigError::invalid(key, &format!("{} is not a number", var)))?,
+        _ => return Ok(()),
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides_known_keys() {
+        let default = serde_json::to_value(ServerConfig::default()).unwrap();
+        let mut value = default.clone();
+        let env = |var: &str| match var {
+            "RAILGUN_HOST" => Some(String::from("192.168.1.2")),
+            "RAILGUN_DISPATCH_BIND_PORT" => Some(String::from("8080")),
+            _ => None,
+        };
+
+        apply_env_overrides(&mut value, &default, "", &env).unwrap();
+        let config = deserialize(value).unwrap();
+        assert_eq!(config.host, "192.168.1.2");
+        assert_eq!(config.dispatch.bind_port, 8080);
+    }
+
+    #[test]
+    fn region_falls_back_to_the_first() {
+        let mut config = ServerConfig::default();
+        let mut test_region = config.dispatch.regions[0].clone();
+        test_region.name = String::from("Test");
+        config.dispatch.regions.push(test_region);
+
+        assert_eq!(config.region(Some("Test")).name, "Test");
+        assert_eq!(config.region(Some("Missing")).name, "Railgun");
+        assert_eq!(config.region(None).name, "Railgun");
+    }
+
+    #[test]
+    fn regions_follow_host_and_bind_ports() {
+        let mut config = ServerConfig {
+            host: String::from("192.168.1.2"),
+            ..Default::default()
+        };
+        config.dispatch.bind_port = 8080;
+        config.gameserver.bind_port = 8081;
+        let mut custom = RegionConfig {
+            name: String::from("Custom"),
+            gateway_url: String::from("https://example.com/query_gateway?a=b"),
+            gameserver_ip: String::from("10.0.0.1"),
+            gameserver_port: 9000,
+            ..Default::default()
+        };
+        config.dispatch.regions.push(custom.clone());
+        config.fill_regions();
+        assert!(config.validate().is_ok());
+
+        let region = &config.dispatch.regions[0];
+        assert_eq!(region.gateway_url, "https://192.168.1.2:8080/query_gateway");
+        assert_eq!(region.gameserver_ip, "192.168.1.2");
+        assert_eq!(region.gameserver_port, 8081);
+        assert_eq!(
+            region.dispatch_url(),
+            "https://192.168.1.2:8080/query_gateway?region=Railgun"
+        );
+
+        let region = &config.dispatch.regions[1];
+        assert_eq!(region.gameserver_ip, "10.0.0.1");
+        assert_eq!(region.gameserver_port, 9000);
+        assert_eq!(
+            region.dispatch_url(),
+            "https://example.com/query_gateway?a=b&region=Custom"
+        );
+
+        custom.gateway_url = String::from("https://example.com/query_gateway?region=Custom");
+        assert!(custom.validate("custom").is_err());
+        custom.gateway_url = String::from("https://example.com/query_gateway");
+        custom.name = String::from("Two words");
+        assert!(custom.validate("custom").is_err());
+    }
+
+    #[test]
+    fn errors_name_the_bad_key() {
+        let default = serde_json::to_value(ServerConfig::default()).unwrap();
+        let mut value = default.clone();
+        let env = |var: &str| (var == "RAILGUN_GAMESERVER_BIND_PORT").then(|| String::from("x"));
+        let Err(ConfigError::InvalidValue { key, .. }) =
+            apply_env_overrides(&mut value, &default, "", &env)
+        else {
+            panic!("expected an invalid value");
+        };
+        assert_eq!(key, "gameserver.bind_port");
+
+        let mut value = default;
+        merge(
+            &mut value,
+            serde_json::json!({ "dispatch": { "bind_port": 70000 } }),
+        );
+        let Err(ConfigError::InvalidValue { key, .. }) = deserialize(value) else {
+            panic!("expected an invalid value");
+        };
+        assert_eq!(key, "dispatch.bind_port");
+    }
+
+    #[test]
+    fn partial_configs_load_with_defaults() {
+        let path = std::env::temp_dir().join(format!("railgun-config-{}.json", std::process::id()));
+        let partial = serde_json::json!({
+            "host": "192.168.1.2",
+            "dispatch": {
+                "regions": [{ "name": "Test", "gateway_url": "http://192.168.1.2:21000/query_gateway" }],
+                "rate_limits": [{ "route": "/account/register", "per_ip": {} }],
+            },
+        });
+        write(&path, partial.to_string()).unwrap();
+
+        let config = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+        assert_eq!(config.host, "192.168.1.2");
+        assert_eq!(config.gameserver.bind_port, 23301);
+        assert_eq!(config.dispatch.hotfix_ttl_minutes, 60);
+        assert_eq!(config.dispatch.regions[0].name, "Test");
+        assert_eq!(config.dispatch.regions[0].env_type, "2");
+        assert_eq!(
+            config.dispatch.rate_limits[0]
+                .per_ip
+                .as_ref()
+                .unwrap()
+                .burst,
+            20
+        );
+    }
+}
@@ -1,8 +1,10 @@
+pub mod config;
 pub mod packet;
+#[allow(clippy::large_enum_variant)]
 pub mod proto;
 pub mod resource;
-pub mod server_config;
 pub mod time;
+pub mod token;
 
 pub fn init_tracing() {
     #[cfg(target_os = "windows")]
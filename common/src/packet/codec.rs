@@ -0,0 +1,197 @@
+use super::PacketError;
+use super::hsr::{self, HEADER_LEN, NetPacket};
+use super::xor::XorKey;
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+pub const DEFAULT_MAX_HEAD_LEN: usize = 4 * 1024;
+pub const DEFAULT_MAX_BODY_LEN: usize = 4 * 1024 * 1024;
+
+// Frames `NetPacket`s for `tokio_util::codec::Framed`. Every frame is XORed
+// from its first byte when a key is set. Bad magic is not resynced: the
+// decoder returns an error and the stream should be dropped.
+#[derive(Debug, Clone)]
+pub struct NetPacketCodec {
+    max_head_len: usize,
+    max_body_len: usize,
+    key: Option<XorKey>,
+}
+
+impl NetPacketCodec {
+    pub fn new(max_head_len: usize, max_body_len: usize) -> Self {
+        Self {
+            max_head_len: max_head_len.min(u16::MAX as usize),
+            max_body_len: max_body_len.min(u32::MAX as usize),
+            key: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: XorKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn set_key(&mut self, key: XorKey) {
+        self.key = Some(key);
+    }
+
+    fn check_lengths(&self, head_len: usize, body_len: usize) -> Result<(), PacketError> {
+        if head_len > self.max_head_len || body_len > self.max_body_len {
+            return Err(PacketError::TooLarge);
+        }
+        Ok(())
+    }
+}
+
+impl Default for NetPacketCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_HEAD_LEN, DEFAULT_MAX_BODY_LEN)
+    }
+}
+
+impl Decoder for NetPacketCodec {
+    type Item = NetPacket;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        // the buffer keeps the raw bytes until a whole frame is in, so the
+        // header is decrypted on a copy
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&src[..HEADER_LEN]);
+        if let Some(key) = &self.key {
+            key.apply(&mut header);
+        }
+
+        let (head_len, body_len) = hsr::parse_header(&header)?;
+        self.check_lengths(head_len, body_len)?;
+
+        let frame_len = hsr::frame_len(head_len, body_len);
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+
+        let mut frame = src.split_to(frame_len);
+        if let Some(key) = &self.key {
+            key.apply(&mut frame);
+        }
+
+        Ok(Some(NetPacket::try_from(&frame[..])?))
+    }
+}
+
+impl Encoder<NetPacket> for NetPacketCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, item: NetPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.check_lengths(item.head.len(), item.body.len())?;
+
+        let start = dst.len();
+        dst.extend_from_slice(&item.encode());
+        if let Some(key) = &self.key {
+            key.apply(&mut dst[start..]);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_util::codec::Framed;
+
+    fn packet() -> NetPacket {
+        NetPacket {
+            cmd: 1,
+            head: vec![0x08, 0x01],
+            body: vec![0xAA; 64],
+        }
+    }
+
+    fn packet_error(err: std::io::Error) -> PacketError {
+        *err.into_inner().unwrap().downcast::<PacketError>().unwrap()
+    }
+
+    #[test]
+    fn oversized_header_is_rejected_before_buffering() {
+        let mut frame = packet().encode();
+        // body length field
+        frame[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let mut src = BytesMut::from(&frame[..HEADER_LEN]);
+        let capacity = src.capacity();
+
+        let err = NetPacketCodec::default().decode(&mut src).unwrap_err();
+        assert!(matches!(packet_error(err), PacketError::TooLarge));
+        assert_eq!(src.capacity(), capacity);
+        assert_eq!(src.len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn bad_magic_is_an_error() {
+        let mut frame = packet().encode();
+        frame[0] ^= 0xFF;
+        let err = NetPacketCodec::default()
+            .decode(&mut BytesMut::from(&frame[..]))
+            .unwrap_err();
+        assert!(matches!(packet_error(err), PacketError::InvalidHeadMagic));
+
+        let mut frame = packet().encode();
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+        let err = NetPacketCodec::default()
+            .decode(&mut BytesMut::from(&frame[..]))
+            .unwrap_err();
+        assert!(matches!(packet_error(err), PacketError::InvalidTailMagic));
+    }
+
+    #[test]
+    fn frame_split_across_reads() {
+        let frame = packet().encode();
+        let mut codec = NetPacketCodec::default();
+        let mut src = BytesMut::from(&frame[..HEADER_LEN + 10]);
+
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&frame[HEADER_LEN + 10..]);
+        let decoded = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(decoded.cmd, 1);
+        assert_eq!(decoded.head, packet().head);
+        assert_eq!(decoded.body, packet().body);
+        assert!(src.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keyed_round_trip_through_framed() {
+        let key = XorKey::from_seed(42);
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = Framed::new(client, NetPacketCodec::default().with_key(key.clone()));
+        let mut server = Framed::new(server, NetPacketCodec::default().with_key(key));
+
+        client.send(packet()).await.unwrap();
+        client.send(packet()).await.unwrap();
+        for _ in 0..2 {
+            let decoded = server.next().await.unwrap().unwrap();
+            assert_eq!(decoded.cmd, 1);
+            assert_eq!(decoded.head, packet().head);
+            assert_eq!(decoded.body, packet().body);
+        }
+
+        // what went over the wire is not the plaintext frame
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut client = Framed::new(
+            client,
+            NetPacketCodec::default().with_key(XorKey::from_seed(42)),
+        );
+        client.send(packet()).await.unwrap();
+        let mut raw = vec![0; packet().encode().len()];
+        tokio::io::AsyncReadExt::read_exact(&mut server, &mut raw)
+            .await
+            .unwrap();
+        assert_ne!(raw, packet().encode());
+    }
+}
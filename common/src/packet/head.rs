@@ -0,0 +1,17 @@
+use prost::Message;
+
+// StarRail.proto doesn't ship this one, the client encodes it into
+// `NetPacket.head`. Responses must echo `client_sequence_id`.
+#[derive(Clone, Copy, PartialEq, Message)]
+pub struct PacketHead {
+    #[prost(uint32, tag = "1")]
+    pub packet_id: u32,
+    #[prost(uint32, tag = "2")]
+    pub rpc_id: u32,
+    #[prost(uint32, tag = "3")]
+    pub client_sequence_id: u32,
+    #[prost(uint64, tag = "6")]
+    pub sent_ms: u64,
+    #[prost(uint32, tag = "11")]
+    pub user_id: u32,
+}
@@ -1,5 +1,8 @@
 use super::PacketError;
+use super::codec::DEFAULT_MAX_BODY_LEN;
+use super::head::PacketHead;
 use byteorder::{BE, ByteOrder};
+use prost::{DecodeError, Message};
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
 
@@ -26,6 +29,8 @@ const HS_END: usize = HS_START + HEAD_SIZE_LEN;
 const BS_START: usize = HS_END;
 const BS_END: usize = BS_START + BODY_SIZE_LEN;
 
+pub(super) const HEADER_LEN: usize = BS_END;
+
 #[derive(Debug, Clone)]
 pub struct NetPacket {
     pub cmd: u16,
@@ -34,6 +39,19 @@ pub struct NetPacket {
 }
 
 impl NetPacket {
+    pub fn new(cmd: u16, body: &impl Message, head: &PacketHead) -> Self {
+        Self {
+            cmd,
+            head: head.encode_to_vec(),
+            body: body.encode_to_vec(),
+        }
+    }
+
+    // an empty head decodes as the default, clients send one on every packet
+    pub fn decode_head(&self) -> Result<PacketHead, DecodeError> {
+        PacketHead::decode(self.head.as_slice())
+    }
+
     pub async fn write(&self, stream: &mut (impl AsyncWriteExt + Unpin)) -> std::io::Result<()> {
         stream.write_all(&HEAD_MAGIC).await?;
         stream.write_u16(self.cmd).await?;
@@ -56,6 +74,9 @@ impl NetPacket {
 
         let head_length = stream.read_u16().await? as usize;
         let body_length = stream.read_u32().await? as usize;
+        if body_length > DEFAULT_MAX_BODY_LEN {
+            return Err(PacketError::TooLarge.into());
+        }
 
         let mut head = vec![0; head_length];
         stream.read_exact(&mut head).await?;
@@ -71,6 +92,53 @@ impl NetPacket {
 
         Ok(Self { cmd, head, body })
     }
+
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let head_len = self.head.len();
+        let body_len = self.body.len();
+
+        let total_len = OVERHEAD + head_len + body_len;
+        let mut out = vec![0u8; total_len];
+
+        out[HM_START..HM_END].copy_from_slice(&HEAD_MAGIC);
+        BE::write_u16(&mut out[CMD_START..CMD_END], self.cmd);
+        BE::write_u16(&mut out[HS_START..HS_END], head_len as u16);
+        BE::write_u32(&mut out[BS_START..BS_END], body_len as u32);
+
+        let head_start = BS_END;
+        let head_end = head_start + head_len;
+
+        let body_start = head_end;
+        let body_end = body_start + body_len;
+
+        let tail_start = body_end;
+        let tail_end = tail_start + TAIL_MAGIC_LEN;
+
+        out[head_start..head_end].copy_from_slice(&self.head);
+        out[body_start..body_end].copy_from_slice(&self.body);
+        out[tail_start..tail_end].copy_from_slice(&TAIL_MAGIC);
+
+        out
+    }
+}
+
+// (head length, body length) of a plaintext header
+pub(super) fn parse_header(header: &[u8]) -> Result<(usize, usize), PacketError> {
+    if header.len() < HEADER_LEN {
+        return Err(PacketError::TooShort);
+    }
+
+    if header[HM_START..HM_END] != HEAD_MAGIC {
+        return Err(PacketError::InvalidHeadMagic);
+    }
+
+    let head_len = BE::read_u16(&header[HS_START..HS_END]) as usize;
+    let body_len = BE::read_u32(&header[BS_START..BS_END]) as usize;
+    Ok((head_len, body_len))
+}
+
+pub(super) fn frame_len(head_len: usize, body_len: usize) -> usize {
+    OVERHEAD + head_len + body_len
 }
 
 impl TryFrom<&[u8]> for NetPacket {
@@ -81,7 +149,7 @@ impl TryFrom<&[u8]> for NetPacket {
             return Err(PacketError::TooShort);
         }
 
-        if &data[HM_START..HM_END] != HEAD_MAGIC {
+        if data[HM_START..HM_END] != HEAD_MAGIC {
             return Err(PacketError::InvalidHeadMagic);
         }
 
@@ -108,7 +176,7 @@ impl TryFrom<&[u8]> for NetPacket {
             return Err(PacketError::SizeMismatch);
         }
 
-        if &data[tail_start..tail_end] != TAIL_MAGIC {
+        if data[tail_start..tail_end] != TAIL_MAGIC {
             return Err(PacketError::InvalidTailMagic);
         }
 
@@ -122,31 +190,7 @@ impl TryFrom<&[u8]> for NetPacket {
 
 impl From<NetPacket> for Vec<u8> {
     fn from(value: NetPacket) -> Self {
-        let head_len = value.head.len();
-        let body_len = value.body.len();
-
-        let total_len = OVERHEAD + head_len + body_len;
-        let mut out = vec![0u8; total_len];
-
-        (&mut out[HM_START..HM_END]).copy_from_slice(&HEAD_MAGIC);
-        BE::write_u16(&mut out[CMD_START..CMD_END], value.cmd);
-        BE::write_u16(&mut out[HS_START..HS_END], head_len as u16);
-        BE::write_u32(&mut out[BS_START..BS_END], body_len as u32);
-
-        let head_start = BS_END;
-        let head_end = head_start + head_len;
-
-        let body_start = head_end;
-        let body_end = body_start + body_len;
-
-        let tail_start = body_end;
-        let tail_end = tail_start + TAIL_MAGIC_LEN;
-
-        (&mut out[head_start..head_end]).copy_from_slice(&value.head);
-        (&mut out[body_start..body_end]).copy_from_slice(&value.body);
-        (&mut out[tail_start..tail_end]).copy_from_slice(&TAIL_MAGIC);
-
-        out
+        value.encode()
     }
 }
 
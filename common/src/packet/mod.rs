@@ -1,4 +1,8 @@
+pub mod codec;
+pub mod head;
 pub mod hsr;
+pub mod mt64;
+pub mod xor;
 
 #[derive(Debug)]
 pub enum PacketError {
@@ -6,6 +10,7 @@ pub enum PacketError {
     InvalidHeadMagic,
     InvalidTailMagic,
     SizeMismatch,
+    TooLarge,
 }
 
 impl std::fmt::Display for PacketError {
@@ -21,7 +26,7 @@ impl From<PacketError> for std::io::Error {
         use PacketError::*;
         let kind = match err {
             TooShort => std::io::ErrorKind::UnexpectedEof,
-            SizeMismatch => std::io::ErrorKind::InvalidData,
+            SizeMismatch | TooLarge => std::io::ErrorKind::InvalidData,
             InvalidHeadMagic | InvalidTailMagic => std::io::ErrorKind::InvalidData,
         };
         std::io::Error::new(kind, err)
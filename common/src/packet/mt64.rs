@@ -0,0 +1,67 @@
+const NN: usize = 312;
+const MM: usize = 156;
+const MATRIX_A: u64 = 0xB502_6F5A_A966_19E9;
+const UPPER_MASK: u64 = 0xFFFF_FFFF_8000_0000;
+const LOWER_MASK: u64 = 0x7FFF_FFFF;
+
+// MT19937-64, as in the reference implementation by Matsumoto and Nishimura.
+pub struct Mt64 {
+    state: [u64; NN],
+    index: usize,
+}
+
+impl Mt64 {
+    pub fn new(seed: u64) -> Self {
+        let mut state = [0u64; NN];
+        state[0] = seed;
+        for i in 1..NN {
+            state[i] = 6364136223846793005u64
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 62))
+                .wrapping_add(i as u64);
+        }
+
+        Self { state, index: NN }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= NN {
+            self.twist();
+        }
+
+        let mut x = self.state[self.index];
+        self.index += 1;
+
+        x ^= (x >> 29) & 0x5555_5555_5555_5555;
+        x ^= (x << 17) & 0x71D6_7FFF_EDA6_0000;
+        x ^= (x << 37) & 0xFFF7_EEE0_0000_0000;
+        x ^= x >> 43;
+        x
+    }
+
+    fn twist(&mut self) {
+        for i in 0..NN {
+            let x = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % NN] & LOWER_MASK);
+            let mut xa = x >> 1;
+            if x & 1 != 0 {
+                xa ^= MATRIX_A;
+            }
+            self.state[i] = self.state[(i + MM) % NN] ^ xa;
+        }
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mt64;
+
+    #[test]
+    fn matches_reference_output() {
+        let mut mt = Mt64::new(5489);
+        assert_eq!(mt.next_u64(), 14514284786278117030);
+        for _ in 0..9998 {
+            mt.next_u64();
+        }
+        assert_eq!(mt.next_u64(), 9981545732273789042);
+    }
+}
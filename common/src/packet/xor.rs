@@ -0,0 +1,124 @@
+use super::mt64::Mt64;
+use std::path::Path;
+
+pub const XOR_KEY_LEN: usize = 4096;
+
+#[derive(Clone)]
+pub struct XorKey(Box<[u8]>);
+
+impl XorKey {
+    // The key used before PlayerGetTokenScRsp hands out a session seed can't
+    // be derived here, it's read as raw bytes dumped for the client.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> std::io::Result<Self> {
+        if bytes.len() != XOR_KEY_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("xor key is {} bytes, not {}", bytes.len(), XOR_KEY_LEN),
+            ));
+        }
+        Ok(Self(bytes.into_boxed_slice()))
+    }
+
+    // The seed is run through the generator once to get the real seed,
+    // the first output after reseeding is discarded, and the next 512 outputs
+    // make up the key in big endian.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut mt = Mt64::new(seed);
+        let mut mt = Mt64::new(mt.next_u64());
+        mt.next_u64();
+
+        let mut key = Vec::with_capacity(XOR_KEY_LEN);
+        for _ in 0..XOR_KEY_LEN / 8 {
+            key.extend_from_slice(&mt.next_u64().to_be_bytes());
+        }
+
+        Self(key.into_boxed_slice())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn apply(&self, data: &mut [u8]) {
+        self.apply_at(data, 0);
+    }
+
+    // `offset` is the position of `data[0]` inside the frame, so a frame
+    // can be transformed in several pieces.
+    pub fn apply_at(&self, data: &mut [u8], offset: usize) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= self.0[(offset + i) % self.0.len()];
+        }
+    }
+}
+
+impl std::fmt::Debug for XorKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "XorKey({:02x?}..)", &self.0[..8])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{XOR_KEY_LEN, XorKey};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn key_schedule_vectors() {
+        let vectors: [(u64, &str, &str); 3] = [
+            (0, "00d553eb7a1b830f3c9b3239e4b40977", "a7d51a758f0ba4d5"),
+            (
+                0x1234567890ABCDEF,
+                "cf6c38170c8c194651e8b39b23d52253",
+                "5792bc4d4b8a3297",
+            ),
+            (5489, "5b715a72ed44aceb7019ee72c9de70f7", "4380e58a883b4a52"),
+        ];
+
+        for (seed, head, tail) in vectors {
+            let key = XorKey::from_seed(seed);
+            let bytes = key.as_bytes();
+            assert_eq!(bytes.len(), XOR_KEY_LEN);
+            assert_eq!(hex(&bytes[..16]), head, "seed {:#x}", seed);
+            assert_eq!(hex(&bytes[XOR_KEY_LEN - 8..]), tail, "seed {:#x}", seed);
+        }
+    }
+
+    #[test]
+    fn from_bytes_takes_only_full_keys() {
+        let key = XorKey::from_bytes(vec![7; XOR_KEY_LEN]).unwrap();
+        assert_eq!(key.as_bytes(), &[7; XOR_KEY_LEN][..]);
+
+        assert!(XorKey::from_bytes(vec![7; XOR_KEY_LEN - 1]).is_err());
+        assert!(XorKey::from_bytes(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn apply_is_reversible_and_offset_aware() {
+        let key = XorKey::from_seed(42);
+        let plain = (0..=255u8)
+            .cycle()
+            .take(XOR_KEY_LEN + 100)
+            .collect::<Vec<u8>>();
+
+        let mut whole = plain.clone();
+        key.apply(&mut whole);
+        assert_ne!(whole, plain);
+
+        let mut pieces = plain.clone();
+        let (a, b) = pieces.split_at_mut(12);
+        key.apply_at(a, 0);
+        key.apply_at(b, 12);
+        assert_eq!(pieces, whole);
+
+        key.apply(&mut whole);
+        assert_eq!(whole, plain);
+    }
+}
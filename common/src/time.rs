@@ -3,3 +3,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 pub fn get_duration_since_unix() -> Duration {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
 }
+
+pub fn get_timezone_offset_hours() -> i32 {
+    chrono::Local::now().offset().local_minus_utc() / 3600
+}
@@ -0,0 +1,229 @@
+use crate::time::get_duration_since_unix;
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write as _};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// a token is `<uid>.<expires_at>.<nonce>.<mac>`, the mac being hmac-sha256 of
+// the rest under a secret dispatch and the gameserver share. that's enough to
+// tell who a token is for and whether it's still good without the database,
+// but revoking one still needs the account's stored token to be compared.
+const LIFETIME_SECS: u64 = 7 * 24 * 60 * 60;
+const NONCE_LEN: usize = 16;
+const SECRET_LEN: usize = 32;
+
+static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenError {
+    Malformed,
+    BadSignature,
+    Expired,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed token"),
+            Self::BadSignature => write!(f, "bad token signature"),
+            Self::Expired => write!(f, "token expired"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenClaims {
+    pub uid: u32,
+    // unix seconds
+    pub expires_at: u64,
+}
+
+impl TokenClaims {
+    // past half its lifetime, so a client that keeps logging in never sees it expire
+    pub fn should_refresh(&self) -> bool {
+        let now = get_duration_since_unix().as_secs();
+        self.expires_at.saturating_sub(now) < LIFETIME_SECS / 2
+    }
+}
+
+// reads the secret tokens are signed with, creating it on first run. dispatch
+// and the gameserver must see the same file
+pub fn init(path: &Path) -> std::io::Result<()> {
+    let secret = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == ErrorKind::NotFound => create_secret(path)?,
+        Err(e) => return Err(e),
+    };
+    let secret = decode_hex(secret.trim())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| std::io::Error::other(format!("{} isn't a hex secret", path.display())))?;
+
+    set_secret(secret);
+    Ok(())
+}
+
+// the first secret set stays, for tests that don't want a file
+pub fn set_secret(secret: Vec<u8>) {
+    let _ = SECRET.set(secret);
+}
+
+fn secret() -> &'static [u8] {
+    SECRET
+        .get()
+        .expect("token::init must be called before using tokens")
+}
+
+fn create_secret(path: &Path) -> std::io::Result<String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut secret = [0; SECRET_LEN];
+    rand_bytes(&mut secret).map_err(std::io::Error::other)?;
+    let secret = encode_hex(&secret);
+
+    // whichever of dispatch and the gameserver starts first writes it, the
+    // other one reads that
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // anyone who can read it can sign tokens for any account
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(secret.as_bytes())?;
+            tracing::info!("generated token secret at {}", path.display());
+            Ok(secret)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => fs::read_to_string(path),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn generate(uid: u32) -> String {
+    generate_with_expiry(uid, get_duration_since_unix().as_secs() + LIFETIME_SECS)
+}
+
+fn generate_with_expiry(uid: u32, expires_at: u64) -> String {
+    let mut nonce = [0; NONCE_LEN];
+    rand_bytes(&mut nonce).expect("the openssl rng failed");
+
+    let payload = format!("{}.{}.{}", uid, expires_at, encode_hex(&nonce));
+    let mac = encode_hex(&sign(secret(), &payload));
+    format!("{}.{}", payload, mac)
+}
+
+pub fn verify(token: &str) -> Result<TokenClaims, TokenError> {
+    let (payload, mac) = token.rsplit_once('.').ok_or(TokenError::Malformed)?;
+    let mac = decode_hex(mac).ok_or(TokenError::Malformed)?;
+
+    let mut parts = payload.split('.');
+    let (Some(uid), Some(expires_at), Some(nonce), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(TokenError::Malformed);
+    };
+    let uid = uid.parse::<u32>().map_err(|_| TokenError::Malformed)?;
+    let expires_at = expires_at
+        .parse::<u64>()
+        .map_err(|_| TokenError::Malformed)?;
+    if decode_hex(nonce).is_none_or(|v| v.len() != NONCE_LEN) {
+        return Err(TokenError::Malformed);
+    }
+
+    let expected = sign(secret(), payload);
+    if mac.len() != expected.len() || !memcmp::eq(&mac, &expected) {
+        return Err(TokenError::BadSignature);
+    }
+    if get_duration_since_unix().as_secs() >= expires_at {
+        return Err(TokenError::Expired);
+    }
+
+    Ok(TokenClaims { uid, expires_at })
+}
+
+fn sign(secret: &[u8], payload: &str) -> Vec<u8> {
+    let key = PKey::hmac(secret).expect("making an hmac key");
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).expect("making an hmac signer");
+    signer
+        .sign_oneshot_to_vec(payload.as_bytes())
+        .expect("signing a token")
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_test_secret() {
+        set_secret(b"test secret".to_vec());
+    }
+
+    #[test]
+    fn tokens_verify_to_their_uid() {
+        init_test_secret();
+        let token = generate(10001);
+        let claims = verify(&token).unwrap();
+        assert_eq!(claims.uid, 10001);
+        assert!(!claims.should_refresh());
+        assert_ne!(generate(10001), token);
+    }
+
+    #[test]
+    fn tampered_and_expired_tokens_are_rejected() {
+        init_test_secret();
+        let token = generate(10001);
+        let forged = token.replacen("10001", "10002", 1);
+        assert_eq!(verify(&forged), Err(TokenError::BadSignature));
+
+        let expired = generate_with_expiry(10001, get_duration_since_unix().as_secs() - 1);
+        assert_eq!(verify(&expired), Err(TokenError::Expired));
+
+        assert_eq!(
+            verify("0123456789abcdef+29000000"),
+            Err(TokenError::Malformed)
+        );
+        assert_eq!(verify(""), Err(TokenError::Malformed));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn created_secret_is_only_readable_by_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("railgun-secret-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let secret = create_secret(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(create_secret(&path).unwrap(), secret);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,31 +1,124 @@
-use mongodb::{Collection, Database, bson::doc, error::Result};
+use mongodb::{
+    Collection, Database, IndexModel,
+    bson::{doc, to_bson},
+    error::Result,
+    options::IndexOptions,
+};
 use serde::{Deserialize, Serialize};
 
 const ACCOUNT_COLL_NAME: &str = "account";
 const ACCOUNT_META_COLL_NAME: &str = "account_meta";
-const STARTING_UID: u32 = 10000;
+pub(crate) const STARTING_UID: u32 = 10000;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AccountDoc {
     #[serde(rename = "_id")]
     pub uid: u32,
     pub username: String,
     pub password_hash: String,
     pub token: String,
+    // the current ban. times are unix seconds, no `ban_end` means it never lifts
     pub is_banned: bool,
     pub ban_reason: Option<String>,
+    pub ban_start: Option<i64>,
+    pub ban_end: Option<i64>,
+    pub ban_issuer: Option<String>,
+    // bans that were lifted or replaced, oldest first
+    #[serde(default)]
+    pub ban_history: Vec<BanRecord>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BanRecord {
+    pub reason: String,
+    pub issuer: String,
+    pub start: i64,
+    pub end: Option<i64>,
+    // set when it was lifted before running out
+    pub lifted_at: Option<i64>,
 }
 
 impl AccountDoc {
+    // what a new account starts with, never banned
+    pub fn new(uid: u32, username: &str, password_hash: String, token: String) -> Self {
+        Self {
+            uid,
+            username: username.to_string(),
+            password_hash,
+            token,
+            is_banned: false,
+            ban_reason: None,
+            ban_start: None,
+            ban_end: None,
+            ban_issuer: None,
+            ban_history: Vec::new(),
+        }
+    }
+
+    // a ban that has run out counts as lifted, it only moves to the history once
+    // the account is banned again or unbanned
+    pub fn is_ban_active(&self, now: i64) -> bool {
+        self.is_banned && self.ban_end.is_none_or(|end| now < end)
+    }
+
+    // seconds until the current ban lifts, `None` for one that never does
+    pub fn ban_remaining(&self, now: i64) -> Option<i64> {
+        self.ban_end.map(|end| (end - now).max(0))
+    }
+
+    pub fn ban(&mut self, reason: &str, issuer: &str, now: i64, end: Option<i64>) {
+        self.archive_ban(now);
+        self.is_banned = true;
+        self.ban_reason = Some(reason.to_string());
+        self.ban_start = Some(now);
+        self.ban_end = end;
+        self.ban_issuer = Some(issuer.to_string());
+    }
+
+    pub fn unban(&mut self, now: i64) {
+        self.archive_ban(now);
+    }
+
+    fn archive_ban(&mut self, now: i64) {
+        if !self.is_banned {
+            return;
+        }
+        let lifted_at = self.is_ban_active(now).then_some(now);
+        self.ban_history.push(BanRecord {
+            reason: self.ban_reason.take().unwrap_or_default(),
+            issuer: self.ban_issuer.take().unwrap_or_default(),
+            start: self.ban_start.take().unwrap_or_default(),
+            end: self.ban_end.take(),
+            lifted_at,
+        });
+        self.is_banned = false;
+    }
+
     pub fn get_collection(db: &Database) -> Collection<Self> {
         db.collection::<Self>(ACCOUNT_COLL_NAME)
     }
 
+    pub async fn create_indexes(collection: &Collection<Self>) -> Result<()> {
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! { "username": 1 })
+            .options(options)
+            .build();
+        collection.create_index(index).await?;
+        Ok(())
+    }
+
     pub async fn insert_to_collection(&self, collection: &Collection<Self>) -> Result<()> {
         collection.insert_one(self).await?;
         Ok(())
     }
 
+    pub async fn delete_by_uid(collection: &Collection<Self>, uid: u32) -> Result<()> {
+        let filter = doc! { "_id": uid };
+        collection.delete_one(filter).await?;
+        Ok(())
+    }
+
     pub async fn check_username_taken(
         collection: &Collection<Self>,
         username: &str,
@@ -60,6 +153,70 @@ impl AccountDoc {
         collection.update_one(filter, update).await?;
         Ok(())
     }
+
+    pub async fn update_password_hash_by_uid(
+        collection: &Collection<Self>,
+        uid: u32,
+        new_hash: &str,
+    ) -> Result<()> {
+        let filter = doc! { "_id": uid };
+        let update = doc! { "$set": { "password_hash": new_hash } };
+        collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
+    // writes the ban fields of `self` and leaves the rest alone
+    pub async fn update_ban(&self, collection: &Collection<Self>) -> Result<()> {
+        let filter = doc! { "_id": self.uid };
+        let update = doc! {
+            "$set": {
+                "is_banned": self.is_banned,
+                "ban_reason": &self.ban_reason,
+                "ban_start": self.ban_start,
+                "ban_end": self.ban_end,
+                "ban_issuer": &self.ban_issuer,
+                "ban_history": to_bson(&self.ban_history)?,
+            }
+        };
+        collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
+    // `search` is matched anywhere in the username, ignoring case
+    pub async fn fetch_page(
+        collection: &Collection<Self>,
+        search: Option<&str>,
+        offset: u64,
+        limit: i64,
+    ) -> Result<Vec<Self>> {
+        let filter = match search {
+            Some(v) => doc! { "username": { "$regex": escape_regex(v), "$options": "i" } },
+            None => doc! {},
+        };
+        let mut cursor = collection
+            .find(filter)
+            .sort(doc! { "_id": 1 })
+            .skip(offset)
+            .limit(limit)
+            .await?;
+
+        let mut accounts = Vec::new();
+        while cursor.advance().await? {
+            accounts.push(cursor.deserialize_current()?);
+        }
+        Ok(accounts)
+    }
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -95,3 +252,30 @@ impl AccountMetaDoc {
         Ok(STARTING_UID)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaced_bans_move_to_the_history() {
+        let mut account = AccountDoc::new(1, "kiana", String::new(), String::new());
+
+        account.ban("spam", "mei", 0, Some(60));
+        assert!(account.is_ban_active(59));
+        assert!(!account.is_ban_active(60));
+        assert_eq!(account.ban_remaining(30), Some(30));
+
+        // the first one ran out on its own, so it wasn't lifted
+        account.ban("cheating", "bronya", 100, None);
+        assert!(account.is_ban_active(i64::MAX));
+        assert_eq!(account.ban_remaining(100), None);
+        assert_eq!(account.ban_history[0].lifted_at, None);
+        assert_eq!(account.ban_history[0].issuer, "mei");
+
+        account.unban(200);
+        assert!(!account.is_banned);
+        assert_eq!(account.ban_history.len(), 2);
+        assert_eq!(account.ban_history[1].lifted_at, Some(200));
+    }
+}
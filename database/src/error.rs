@@ -0,0 +1,75 @@
+use mongodb::error::{ErrorKind, WriteFailure};
+use rusqlite::ffi::{SQLITE_CONSTRAINT_PRIMARYKEY, SQLITE_CONSTRAINT_UNIQUE};
+
+const MONGO_DUPLICATE_KEY: i32 = 11000;
+
+#[derive(Debug)]
+pub enum Error {
+    Mongo(mongodb::error::Error),
+    Sqlite(rusqlite::Error),
+    Json(serde_json::Error),
+    DuplicateKey(String),
+    SchemaTooNew { found: usize, supported: usize },
+    // a query on a blocking thread panicked or the runtime shut down under it
+    Task(tokio::task::JoinError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Mongo(e) => write!(f, "{}", e),
+            Self::Sqlite(e) => write!(f, "{}", e),
+            Self::Json(e) => write!(f, "{}", e),
+            Self::DuplicateKey(key) => write!(f, "duplicate key `{}`", key),
+            Self::SchemaTooNew { found, supported } => write!(
+                f,
+                "schema version {} is newer than the {} this build knows, update the server",
+                found, supported
+            ),
+            Self::Task(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<mongodb::error::Error> for Error {
+    fn from(err: mongodb::error::Error) -> Self {
+        match *err.kind {
+            ErrorKind::Write(WriteFailure::WriteError(ref e)) if e.code == MONGO_DUPLICATE_KEY => {
+                Self::DuplicateKey(e.message.clone())
+            }
+            _ => Self::Mongo(err),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::SqliteFailure(e, Some(msg))
+                if matches!(
+                    e.extended_code,
+                    SQLITE_CONSTRAINT_PRIMARYKEY | SQLITE_CONSTRAINT_UNIQUE
+                ) =>
+            {
+                Self::DuplicateKey(msg)
+            }
+            err => Self::Sqlite(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::Task(err)
+    }
+}
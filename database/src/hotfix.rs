@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 const HOTFIX_COLL_NAME: &str = "hotfix";
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct HotfixDoc {
     #[serde(rename = "_id")]
     pub version: String,
@@ -13,9 +13,31 @@ pub struct HotfixDoc {
     pub mdk_res_version: String,
     pub asset_bundle_url: String,
     pub ex_resource_url: String,
+    // unix seconds of the last time upstream was asked, failed or not. 0 for
+    // docs stored before this was, so they count as stale
+    #[serde(default)]
+    pub fetched_at: i64,
 }
 
 impl HotfixDoc {
+    // what the client gets when there's nothing better, it boots without hotfixes
+    pub fn empty(version: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            ifix_url: String::new(),
+            ifix_version: String::from("0"),
+            mdk_res_url: String::new(),
+            mdk_res_version: String::new(),
+            asset_bundle_url: String::new(),
+            ex_resource_url: String::new(),
+            fetched_at: 0,
+        }
+    }
+
+    pub fn is_stale(&self, now: i64, ttl_seconds: i64) -> bool {
+        now.saturating_sub(self.fetched_at) >= ttl_seconds
+    }
+
     pub fn get_collection(db: &Database) -> Collection<Self> {
         db.collection::<Self>(HOTFIX_COLL_NAME)
     }
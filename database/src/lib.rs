@@ -1,14 +1,57 @@
+use common::config::DatabaseBackend;
+pub use error::{Error, Result};
 pub use mongodb::Client as MongoClient;
+pub use repo::Storage;
+use std::path::Path;
 
 pub mod account;
+pub mod error;
 pub mod hotfix;
+pub mod migration;
+pub mod repo;
 pub mod srtools;
 
-const MONGODB_CONNECTION_URI: &str = "mongodb://localhost:27017";
 pub const DB_NAME: &str = "railgun";
 
-pub async fn new_mongo_client() -> MongoClient {
-    MongoClient::with_uri_str(MONGODB_CONNECTION_URI)
-        .await
-        .unwrap()
+pub async fn new_mongo_client() -> Result<MongoClient> {
+    Ok(MongoClient::with_uri_str(&common::config::get().database.mongodb_uri).await?)
+}
+
+pub async fn new_storage() -> Result<Storage> {
+    let config = &common::config::get().database;
+    let storage = match config.backend {
+        DatabaseBackend::Mongodb => {
+            Storage::new(repo::mongo::MongoStorage::open(&new_mongo_client().await?).await?)
+        }
+        DatabaseBackend::Sqlite => Storage::new(repo::sqlite::SqliteStorage::open(Path::new(
+            &config.sqlite_path,
+        ))?),
+        DatabaseBackend::Memory => {
+            tracing::warn!("using the in-memory database, nothing will be saved");
+            Storage::new(repo::memory::MemoryStorage::default())
+        }
+    };
+    Ok(storage)
+}
+
+// logs what `new_storage` would migrate, without changing anything
+pub async fn dry_run_migrations() -> Result<()> {
+    let config = &common::config::get().database;
+    let changes = match config.backend {
+        DatabaseBackend::Mongodb => {
+            repo::mongo::MongoStorage::plan_migrations(&new_mongo_client().await?).await?
+        }
+        DatabaseBackend::Sqlite => {
+            repo::sqlite::SqliteStorage::plan_migrations(Path::new(&config.sqlite_path))?
+        }
+        DatabaseBackend::Memory => Vec::new(),
+    };
+
+    if changes.is_empty() {
+        tracing::info!("schema is up to date, nothing to migrate");
+    }
+    for change in changes {
+        tracing::info!("would migrate {}", change);
+    }
+    Ok(())
 }
@@ -0,0 +1,163 @@
+use crate::account::AccountDoc;
+use crate::error::{Error, Result};
+use crate::srtools::SRToolsDoc;
+use mongodb::{
+    Collection, Database,
+    bson::{Document, doc},
+    options::ReplaceOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+
+const SCHEMA_VERSION_COLL_NAME: &str = "schema_version";
+
+type StepFuture<'a, T> = Pin<Box<dyn Future<Output = mongodb::error::Result<T>> + Send + 'a>>;
+
+struct Migration {
+    description: &'static str,
+    // what `apply` would change, reported by dry runs
+    plan: for<'a> fn(&'a Database) -> StepFuture<'a, Vec<String>>,
+    apply: for<'a> fn(&'a Database) -> StepFuture<'a, ()>,
+}
+
+// version n is MIGRATIONS[n - 1]. only ever append, a released step must not
+// change. steps should be safe to re-run, dispatch and the gameserver can both
+// start on an old schema at the same time.
+const MIGRATIONS: &[Migration] = &[Migration {
+    description: "unique usernames",
+    plan: plan_unique_usernames,
+    apply: apply_unique_usernames,
+}];
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SchemaVersionDoc {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub version: u32,
+}
+
+impl SchemaVersionDoc {
+    pub fn get_collection(db: &Database) -> Collection<Self> {
+        db.collection::<Self>(SCHEMA_VERSION_COLL_NAME)
+    }
+
+    // a database from before versioning has no doc, which is version 0
+    pub async fn fetch_version(collection: &Collection<Self>) -> mongodb::error::Result<u32> {
+        let filter = doc! { "_id": "schema" };
+        let result = collection.find_one(filter).await?;
+        Ok(result.map(|v| v.version).unwrap_or_default())
+    }
+
+    pub async fn set_version(
+        collection: &Collection<Self>,
+        version: u32,
+    ) -> mongodb::error::Result<()> {
+        let new_doc = Self {
+            id: String::from("schema"),
+            version,
+        };
+        let filter = doc! { "_id": "schema" };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        collection
+            .replace_one(filter, new_doc)
+            .with_options(options)
+            .await?;
+        Ok(())
+    }
+}
+
+// a schema written by a newer build could have anything in it
+pub(crate) fn check_version(found: usize, supported: usize) -> Result<()> {
+    if found > supported {
+        return Err(Error::SchemaTooNew { found, supported });
+    }
+    Ok(())
+}
+
+pub async fn migrate(db: &Database) -> Result<()> {
+    let sv_coll = SchemaVersionDoc::get_collection(db);
+    let current = SchemaVersionDoc::fetch_version(&sv_coll).await? as usize;
+    check_version(current, MIGRATIONS.len())?;
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = i as u32 + 1;
+        (migration.apply)(db).await?;
+        SchemaVersionDoc::set_version(&sv_coll, version).await?;
+        tracing::info!(
+            "migrated mongo schema to version {} ({})",
+            version,
+            migration.description
+        );
+    }
+
+    Ok(())
+}
+
+// one line per change `migrate` would make, empty when the schema is up to date
+pub async fn plan(db: &Database) -> Result<Vec<String>> {
+    let sv_coll = SchemaVersionDoc::get_collection(db);
+    let current = SchemaVersionDoc::fetch_version(&sv_coll).await? as usize;
+    check_version(current, MIGRATIONS.len())?;
+
+    let mut changes = Vec::new();
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        for change in (migration.plan)(db).await? {
+            changes.push(format!(
+                "version {} ({}): {}",
+                i + 1,
+                migration.description,
+                change
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+async fn duplicate_usernames<T: Send + Sync>(
+    collection: &Collection<T>,
+) -> mongodb::error::Result<Vec<String>> {
+    let pipeline = [
+        doc! { "$group": { "_id": "$username", "count": { "$sum": 1 } } },
+        doc! { "$match": { "count": { "$gt": 1 } } },
+    ];
+    let mut cursor = collection.aggregate(pipeline).await?;
+
+    let mut usernames = Vec::new();
+    while cursor.advance().await? {
+        let group: Document = cursor.deserialize_current()?;
+        usernames.push(group.get_str("_id").unwrap_or_default().to_string());
+    }
+
+    Ok(usernames)
+}
+
+fn plan_unique_usernames(db: &Database) -> StepFuture<'_, Vec<String>> {
+    Box::pin(async move {
+        let mut changes = vec![
+            String::from("create a unique index on account.username"),
+            String::from("create a unique index on srtools.username"),
+        ];
+        for username in duplicate_usernames(&AccountDoc::get_collection(db)).await? {
+            changes.push(format!(
+                "account has more than one `{}`, the index can't be made until they're merged",
+                username
+            ));
+        }
+        for username in duplicate_usernames(&SRToolsDoc::get_collection(db)).await? {
+            changes.push(format!(
+                "srtools has more than one `{}`, the index can't be made until they're merged",
+                username
+            ));
+        }
+        Ok(changes)
+    })
+}
+
+fn apply_unique_usernames(db: &Database) -> StepFuture<'_, ()> {
+    Box::pin(async move {
+        AccountDoc::create_indexes(&AccountDoc::get_collection(db)).await?;
+        SRToolsDoc::create_indexes(&SRToolsDoc::get_collection(db)).await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,314 @@
+use super::{AccountRepo, HotfixRepo, RepoFuture, SRToolsRepo, ready};
+use crate::account::{AccountDoc, STARTING_UID};
+use crate::error::Error;
+use crate::hotfix::HotfixDoc;
+use crate::srtools::{
+    SRTOOLS_EXPORT_COOLDOWN_MINUTES, SRTOOLS_SYNC_COOLDOWN_MINUTES, SRToolsData, SRToolsDoc,
+    SRToolsMetaDoc, cooldown_end,
+};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Default)]
+struct MemoryData {
+    accounts: HashMap<u32, AccountDoc>,
+    next_uid: Option<u32>,
+    hotfixes: HashMap<String, HotfixDoc>,
+    srtools: HashMap<u32, SRToolsDoc>,
+    srtools_meta: HashMap<String, SRToolsMetaDoc>,
+}
+
+// behaves like the mongo backend: ids and usernames are unique, and updating a
+// missing doc is a no-op
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: Mutex<MemoryData>,
+}
+
+impl MemoryStorage {
+    fn lock(&self) -> MutexGuard<'_, MemoryData> {
+        self.data.lock().unwrap()
+    }
+}
+
+impl AccountRepo for MemoryStorage {
+    fn register<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        let mut data = self.lock();
+        if data.accounts.contains_key(&account.uid) || data.srtools.contains_key(&account.uid) {
+            return ready(Err(Error::DuplicateKey(String::from("account._id"))));
+        }
+        if data
+            .accounts
+            .values()
+            .any(|v| v.username == account.username)
+            || data.srtools_meta.contains_key(&account.username)
+        {
+            return ready(Err(Error::DuplicateKey(String::from("account.username"))));
+        }
+
+        data.accounts.insert(account.uid, account.clone());
+        data.srtools.insert(
+            account.uid,
+            SRToolsDoc::empty(account.uid, &account.username),
+        );
+        data.srtools_meta.insert(
+            account.username.clone(),
+            SRToolsMetaDoc::new(&account.username),
+        );
+        ready(Ok(()))
+    }
+
+    fn username_taken<'a>(&'a self, username: &'a str) -> RepoFuture<'a, bool> {
+        let data = self.lock();
+        ready(Ok(data.accounts.values().any(|v| v.username == username)))
+    }
+
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<AccountDoc>> {
+        ready(Ok(self.lock().accounts.get(&uid).cloned()))
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<AccountDoc>> {
+        let data = self.lock();
+        let account = data.accounts.values().find(|v| v.username == username);
+        ready(Ok(account.cloned()))
+    }
+
+    fn update_token<'a>(&'a self, uid: u32, token: &'a str) -> RepoFuture<'a, ()> {
+        if let Some(account) = self.lock().accounts.get_mut(&uid) {
+            account.token = token.to_string();
+        }
+        ready(Ok(()))
+    }
+
+    fn next_uid(&self) -> RepoFuture<'_, u32> {
+        let mut data = self.lock();
+        let uid = data.next_uid.unwrap_or(STARTING_UID);
+        data.next_uid = Some(uid + 1);
+        ready(Ok(uid))
+    }
+
+    fn list<'a>(
+        &'a self,
+        search: Option<&'a str>,
+        offset: u64,
+        limit: u64,
+    ) -> RepoFuture<'a, Vec<AccountDoc>> {
+        let data = self.lock();
+        let search = search.map(str::to_lowercase);
+        let mut accounts: Vec<_> = data
+            .accounts
+            .values()
+            .filter(|v| {
+                search
+                    .as_ref()
+                    .is_none_or(|s| v.username.to_lowercase().contains(s))
+            })
+            .cloned()
+            .collect();
+        accounts.sort_by_key(|v| v.uid);
+
+        let page = accounts
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        ready(Ok(page))
+    }
+
+    fn update_ban<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        if let Some(stored) = self.lock().accounts.get_mut(&account.uid) {
+            stored.is_banned = account.is_banned;
+            stored.ban_reason = account.ban_reason.clone();
+            stored.ban_start = account.ban_start;
+            stored.ban_end = account.ban_end;
+            stored.ban_issuer = account.ban_issuer.clone();
+            stored.ban_history = account.ban_history.clone();
+        }
+        ready(Ok(()))
+    }
+
+    fn update_password_hash<'a>(&'a self, uid: u32, hash: &'a str) -> RepoFuture<'a, ()> {
+        if let Some(account) = self.lock().accounts.get_mut(&uid) {
+            account.password_hash = hash.to_string();
+        }
+        ready(Ok(()))
+    }
+
+    fn delete(&self, uid: u32) -> RepoFuture<'_, ()> {
+        let mut data = self.lock();
+        if let Some(account) = data.accounts.remove(&uid) {
+            data.srtools.remove(&uid);
+            data.srtools_meta.remove(&account.username);
+        }
+        ready(Ok(()))
+    }
+}
+
+impl HotfixRepo for MemoryStorage {
+    fn fetch_by_version<'a>(&'a self, version: &'a str) -> RepoFuture<'a, Option<HotfixDoc>> {
+        ready(Ok(self.lock().hotfixes.get(version).cloned()))
+    }
+
+    fn upsert<'a>(&'a self, hotfix: &'a HotfixDoc) -> RepoFuture<'a, ()> {
+        let mut data = self.lock();
+        data.hotfixes.insert(hotfix.version.clone(), hotfix.clone());
+        ready(Ok(()))
+    }
+}
+
+impl SRToolsRepo for MemoryStorage {
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<SRToolsDoc>> {
+        ready(Ok(self.lock().srtools.get(&uid).cloned()))
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsDoc>> {
+        let data = self.lock();
+        let doc = data.srtools.values().find(|v| v.username == username);
+        ready(Ok(doc.cloned()))
+    }
+
+    fn set_data_by_username<'a>(
+        &'a self,
+        username: &'a str,
+        data: &'a SRToolsData,
+    ) -> RepoFuture<'a, ()> {
+        let mut lock = self.lock();
+        if let Some(doc) = lock.srtools.values_mut().find(|v| v.username == username) {
+            doc.data = Some(data.clone());
+        }
+        ready(Ok(()))
+    }
+
+    fn fetch_meta<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsMetaDoc>> {
+        ready(Ok(self.lock().srtools_meta.get(username).cloned()))
+    }
+
+    fn update_next_sync<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        if let Some(meta) = self.lock().srtools_meta.get_mut(username) {
+            meta.next_sync_allowed = cooldown_end(SRTOOLS_SYNC_COOLDOWN_MINUTES);
+        }
+        ready(Ok(()))
+    }
+
+    fn update_next_export<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        if let Some(meta) = self.lock().srtools_meta.get_mut(username) {
+            meta.next_export_allowed = cooldown_end(SRTOOLS_EXPORT_COOLDOWN_MINUTES);
+        }
+        ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::Storage;
+
+    fn account(uid: u32, username: &str) -> AccountDoc {
+        AccountDoc::new(uid, username, String::new(), String::from("token"))
+    }
+
+    #[tokio::test]
+    async fn uids_count_up_from_the_starting_uid() {
+        let storage = Storage::new(MemoryStorage::default());
+        assert_eq!(storage.accounts.next_uid().await.unwrap(), STARTING_UID);
+        assert_eq!(storage.accounts.next_uid().await.unwrap(), STARTING_UID + 1);
+    }
+
+    #[tokio::test]
+    async fn accounts_round_trip() {
+        let storage = Storage::new(MemoryStorage::default());
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+
+        assert!(storage.accounts.username_taken("kiana").await.unwrap());
+        assert!(!storage.accounts.username_taken("mei").await.unwrap());
+
+        storage.accounts.update_token(1, "new").await.unwrap();
+        let fetched = storage.accounts.fetch_by_username("kiana").await.unwrap();
+        assert_eq!(fetched.unwrap().token, "new");
+        assert!(storage.accounts.fetch_by_uid(2).await.unwrap().is_none());
+
+        let Err(Error::DuplicateKey(_)) = storage.accounts.register(&account(1, "mei")).await
+        else {
+            panic!("expected a duplicate key");
+        };
+    }
+
+    #[tokio::test]
+    async fn register_is_all_or_nothing() {
+        let storage = Storage::new(MemoryStorage::default());
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        assert!(storage.srtools.fetch_by_uid(1).await.unwrap().is_some());
+        assert!(storage.srtools.fetch_meta("kiana").await.unwrap().is_some());
+
+        let Err(Error::DuplicateKey(_)) = storage.accounts.register(&account(2, "kiana")).await
+        else {
+            panic!("expected a duplicate key");
+        };
+        assert!(storage.accounts.fetch_by_uid(2).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_by_uid(2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn srtools_meta_cooldowns_are_set() {
+        let storage = Storage::new(MemoryStorage::default());
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        storage.srtools.update_next_sync("kiana").await.unwrap();
+
+        let meta = storage.srtools.fetch_meta("kiana").await.unwrap().unwrap();
+        assert!(meta.next_sync_allowed > 0);
+        assert_eq!(meta.next_export_allowed, 0);
+    }
+
+    #[tokio::test]
+    async fn list_searches_usernames_in_uid_order() {
+        let storage = Storage::new(MemoryStorage::default());
+        for (uid, username) in [(3, "Kiana"), (1, "kiana_alt"), (2, "mei")] {
+            storage
+                .accounts
+                .register(&account(uid, username))
+                .await
+                .unwrap();
+        }
+
+        let page = storage.accounts.list(Some("KIANA"), 0, 10).await.unwrap();
+        let uids: Vec<_> = page.iter().map(|v| v.uid).collect();
+        assert_eq!(uids, [1, 3]);
+
+        let page = storage.accounts.list(None, 1, 1).await.unwrap();
+        assert_eq!(page[0].username, "mei");
+    }
+
+    #[tokio::test]
+    async fn delete_removes_the_srtools_docs() {
+        let storage = Storage::new(MemoryStorage::default());
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        storage.accounts.delete(1).await.unwrap();
+
+        assert!(storage.accounts.fetch_by_uid(1).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_by_uid(1).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_meta("kiana").await.unwrap().is_none());
+
+        // the username is free again
+        storage
+            .accounts
+            .register(&account(2, "kiana"))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::account::AccountDoc;
+use crate::error::Result;
+use crate::hotfix::HotfixDoc;
+use crate::srtools::{SRToolsData, SRToolsDoc, SRToolsMetaDoc};
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub mod memory;
+pub mod mongo;
+pub mod sqlite;
+
+pub type RepoFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+// for backends that never await, the call is done by the time the future is made
+fn ready<'a, T: Send + 'a>(result: Result<T>) -> RepoFuture<'a, T> {
+    Box::pin(std::future::ready(result))
+}
+
+pub trait AccountRepo: Send + Sync {
+    // inserts the account along with its empty srtools docs, all or nothing.
+    // a taken username (or uid) is `Error::DuplicateKey`
+    fn register<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()>;
+    fn username_taken<'a>(&'a self, username: &'a str) -> RepoFuture<'a, bool>;
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<AccountDoc>>;
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<AccountDoc>>;
+    fn update_token<'a>(&'a self, uid: u32, token: &'a str) -> RepoFuture<'a, ()>;
+    fn next_uid(&self) -> RepoFuture<'_, u32>;
+    // sorted by uid. `search` matches anywhere in the username, ignoring case
+    fn list<'a>(
+        &'a self,
+        search: Option<&'a str>,
+        offset: u64,
+        limit: u64,
+    ) -> RepoFuture<'a, Vec<AccountDoc>>;
+    // writes the ban fields, see `AccountDoc::ban` and `AccountDoc::unban`
+    fn update_ban<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()>;
+    fn update_password_hash<'a>(&'a self, uid: u32, hash: &'a str) -> RepoFuture<'a, ()>;
+    // removes the account along with its srtools docs
+    fn delete(&self, uid: u32) -> RepoFuture<'_, ()>;
+}
+
+pub trait HotfixRepo: Send + Sync {
+    fn fetch_by_version<'a>(&'a self, version: &'a str) -> RepoFuture<'a, Option<HotfixDoc>>;
+    fn upsert<'a>(&'a self, hotfix: &'a HotfixDoc) -> RepoFuture<'a, ()>;
+}
+
+pub trait SRToolsRepo: Send + Sync {
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<SRToolsDoc>>;
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsDoc>>;
+    fn set_data_by_username<'a>(
+        &'a self,
+        username: &'a str,
+        data: &'a SRToolsData,
+    ) -> RepoFuture<'a, ()>;
+    fn fetch_meta<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsMetaDoc>>;
+    fn update_next_sync<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()>;
+    fn update_next_export<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()>;
+}
+
+// what handlers hold instead of a database client, cheap to clone
+#[derive(Clone)]
+pub struct Storage {
+    pub accounts: Arc<dyn AccountRepo>,
+    pub hotfixes: Arc<dyn HotfixRepo>,
+    pub srtools: Arc<dyn SRToolsRepo>,
+}
+
+impl Storage {
+    pub fn new<T>(backend: T) -> Self
+    where
+        T: AccountRepo + HotfixRepo + SRToolsRepo + 'static,
+    {
+        let backend = Arc::new(backend);
+        Self {
+            accounts: backend.clone(),
+            hotfixes: backend.clone(),
+            srtools: backend,
+        }
+    }
+}
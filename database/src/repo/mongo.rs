@@ -0,0 +1,204 @@
+use super::{AccountRepo, HotfixRepo, RepoFuture, SRToolsRepo};
+use crate::account::{AccountDoc, AccountMetaDoc};
+use crate::error::Result;
+use crate::hotfix::HotfixDoc;
+use crate::migration;
+use crate::srtools::{SRToolsData, SRToolsDoc, SRToolsMetaDoc};
+use crate::{DB_NAME, MongoClient};
+use mongodb::Database;
+
+pub struct MongoStorage {
+    db: Database,
+}
+
+impl MongoStorage {
+    // the client connects lazily, so migrating is also what finds out whether
+    // mongo is reachable at all
+    pub async fn open(client: &MongoClient) -> Result<Self> {
+        let db = client.database(DB_NAME);
+        migration::migrate(&db).await?;
+        Ok(Self { db })
+    }
+
+    pub async fn plan_migrations(client: &MongoClient) -> Result<Vec<String>> {
+        migration::plan(&client.database(DB_NAME)).await
+    }
+}
+
+fn log_undo(result: mongodb::error::Result<()>) {
+    if let Err(e) = result {
+        tracing::error!("Undoing partial registration: {}", e);
+    }
+}
+
+impl AccountRepo for MongoStorage {
+    // transactions need a replica set, which most setups don't run, so a failed
+    // insert deletes the ones that went through before it instead
+    fn register<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            let st_coll = SRToolsDoc::get_collection(&self.db);
+            let stm_coll = SRToolsMetaDoc::get_collection(&self.db);
+
+            account.insert_to_collection(&ac_coll).await?;
+
+            let srtools_doc = SRToolsDoc::empty(account.uid, &account.username);
+            if let Err(e) = srtools_doc.insert_to_collection(&st_coll).await {
+                log_undo(AccountDoc::delete_by_uid(&ac_coll, account.uid).await);
+                return Err(e.into());
+            }
+
+            let srtools_meta_doc = SRToolsMetaDoc::new(&account.username);
+            if let Err(e) = srtools_meta_doc.insert_to_collection(&stm_coll).await {
+                log_undo(SRToolsDoc::delete_by_uid(&st_coll, account.uid).await);
+                log_undo(AccountDoc::delete_by_uid(&ac_coll, account.uid).await);
+                return Err(e.into());
+            }
+
+            Ok(())
+        })
+    }
+
+    fn username_taken<'a>(&'a self, username: &'a str) -> RepoFuture<'a, bool> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(AccountDoc::check_username_taken(&ac_coll, username).await?)
+        })
+    }
+
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<AccountDoc>> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(AccountDoc::fetch_by_uid(&ac_coll, uid).await?)
+        })
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<AccountDoc>> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(AccountDoc::fetch_by_username(&ac_coll, username).await?)
+        })
+    }
+
+    fn update_token<'a>(&'a self, uid: u32, token: &'a str) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(AccountDoc::update_token_by_uid(&ac_coll, uid, token).await?)
+        })
+    }
+
+    fn next_uid(&self) -> RepoFuture<'_, u32> {
+        Box::pin(async move {
+            let acm_coll = AccountMetaDoc::get_collection(&self.db);
+            Ok(AccountMetaDoc::get_next_uid(&acm_coll).await?)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        search: Option<&'a str>,
+        offset: u64,
+        limit: u64,
+    ) -> RepoFuture<'a, Vec<AccountDoc>> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            Ok(AccountDoc::fetch_page(&ac_coll, search, offset, limit).await?)
+        })
+    }
+
+    fn update_ban<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(account.update_ban(&ac_coll).await?)
+        })
+    }
+
+    fn update_password_hash<'a>(&'a self, uid: u32, hash: &'a str) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            Ok(AccountDoc::update_password_hash_by_uid(&ac_coll, uid, hash).await?)
+        })
+    }
+
+    // the account goes last, so a delete that fails halfway can just be retried
+    fn delete(&self, uid: u32) -> RepoFuture<'_, ()> {
+        Box::pin(async move {
+            let ac_coll = AccountDoc::get_collection(&self.db);
+            let Some(account) = AccountDoc::fetch_by_uid(&ac_coll, uid).await? else {
+                return Ok(());
+            };
+
+            let stm_coll = SRToolsMetaDoc::get_collection(&self.db);
+            SRToolsMetaDoc::delete_by_username(&stm_coll, &account.username).await?;
+            let st_coll = SRToolsDoc::get_collection(&self.db);
+            SRToolsDoc::delete_by_uid(&st_coll, uid).await?;
+            AccountDoc::delete_by_uid(&ac_coll, uid).await?;
+            Ok(())
+        })
+    }
+}
+
+impl HotfixRepo for MongoStorage {
+    fn fetch_by_version<'a>(&'a self, version: &'a str) -> RepoFuture<'a, Option<HotfixDoc>> {
+        Box::pin(async move {
+            let hf_coll = HotfixDoc::get_collection(&self.db);
+            Ok(HotfixDoc::fetch_by_version(&hf_coll, version).await?)
+        })
+    }
+
+    fn upsert<'a>(&'a self, hotfix: &'a HotfixDoc) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let hf_coll = HotfixDoc::get_collection(&self.db);
+            Ok(hotfix.upsert_to_collection(&hf_coll).await?)
+        })
+    }
+}
+
+impl SRToolsRepo for MongoStorage {
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<SRToolsDoc>> {
+        Box::pin(async move {
+            let st_coll = SRToolsDoc::get_collection(&self.db);
+            Ok(SRToolsDoc::fetch_by_uid(&st_coll, uid).await?)
+        })
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsDoc>> {
+        Box::pin(async move {
+            let st_coll = SRToolsDoc::get_collection(&self.db);
+            Ok(SRToolsDoc::fetch_by_username(&st_coll, username).await?)
+        })
+    }
+
+    fn set_data_by_username<'a>(
+        &'a self,
+        username: &'a str,
+        data: &'a SRToolsData,
+    ) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let st_coll = SRToolsDoc::get_collection(&self.db);
+            Ok(SRToolsDoc::set_srtools_by_username(&st_coll, username, data).await?)
+        })
+    }
+
+    fn fetch_meta<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsMetaDoc>> {
+        Box::pin(async move {
+            let stm_coll = SRToolsMetaDoc::get_collection(&self.db);
+            Ok(SRToolsMetaDoc::fetch_by_username(&stm_coll, username).await?)
+        })
+    }
+
+    fn update_next_sync<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let stm_coll = SRToolsMetaDoc::get_collection(&self.db);
+            Ok(SRToolsMetaDoc::update_next_sync_for_username(&stm_coll, username).await?)
+        })
+    }
+
+    fn update_next_export<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        Box::pin(async move {
+            let stm_coll = SRToolsMetaDoc::get_collection(&self.db);
+            Ok(SRToolsMetaDoc::update_next_export_for_username(&stm_coll, username).await?)
+        })
+    }
+}
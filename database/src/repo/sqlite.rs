@@ -0,0 +1,682 @@
+use super::{AccountRepo, HotfixRepo, RepoFuture, SRToolsRepo, ready};
+use crate::account::{AccountDoc, STARTING_UID};
+use crate::error::Result;
+use crate::hotfix::HotfixDoc;
+use crate::migration::check_version;
+use crate::srtools::{
+    SRTOOLS_EXPORT_COOLDOWN_MINUTES, SRTOOLS_SYNC_COOLDOWN_MINUTES, SRToolsData, SRToolsDoc,
+    SRToolsMetaDoc, cooldown_end,
+};
+use rusqlite::types::Type;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, ToSql, TransactionBehavior, params};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// (description, sql). `PRAGMA user_version` is how many of these a database
+// file has had applied. only ever append, a released step must not change.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "initial schema, same tables as the mongo collections",
+        "CREATE TABLE account (
+        uid INTEGER PRIMARY KEY,
+        username TEXT NOT NULL,
+        password_hash TEXT NOT NULL,
+        token TEXT NOT NULL,
+        is_banned INTEGER NOT NULL,
+        ban_reason TEXT
+    );
+    CREATE INDEX account_username ON account (username);
+    CREATE TABLE account_meta (
+        id TEXT PRIMARY KEY,
+        next_uid INTEGER NOT NULL
+    );
+    CREATE TABLE hotfix (
+        version TEXT PRIMARY KEY,
+        ifix_url TEXT NOT NULL,
+        ifix_version TEXT NOT NULL,
+        mdk_res_url TEXT NOT NULL,
+        mdk_res_version TEXT NOT NULL,
+        asset_bundle_url TEXT NOT NULL,
+        ex_resource_url TEXT NOT NULL
+    );
+    CREATE TABLE srtools (
+        uid INTEGER PRIMARY KEY,
+        username TEXT NOT NULL,
+        data TEXT
+    );
+    CREATE INDEX srtools_username ON srtools (username);
+    CREATE TABLE srtools_meta (
+        username TEXT PRIMARY KEY,
+        next_sync_allowed INTEGER NOT NULL,
+        next_export_allowed INTEGER NOT NULL
+    );",
+    ),
+    (
+        "unique usernames",
+        "DROP INDEX account_username;
+        CREATE UNIQUE INDEX account_username ON account (username);
+        DROP INDEX srtools_username;
+        CREATE UNIQUE INDEX srtools_username ON srtools (username);",
+    ),
+    (
+        "ban end times",
+        "ALTER TABLE account ADD COLUMN ban_end INTEGER;",
+    ),
+    (
+        "ban issuers and history",
+        "ALTER TABLE account ADD COLUMN ban_start INTEGER;
+        ALTER TABLE account ADD COLUMN ban_issuer TEXT;
+        ALTER TABLE account ADD COLUMN ban_history TEXT NOT NULL DEFAULT '[]';",
+    ),
+    (
+        "hotfix fetch times",
+        "ALTER TABLE hotfix ADD COLUMN fetched_at INTEGER NOT NULL DEFAULT 0;",
+    ),
+];
+
+// dispatch and the gameserver can open the same file, this is how long one
+// waits for the other's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// queries run on tokio's blocking threads, one at a time
+pub struct SqliteStorage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    // one line per migration `open` would apply, empty when the file is up to date
+    pub fn plan_migrations(path: &Path) -> Result<Vec<String>> {
+        // a missing file gets created with every migration
+        let version = match path.exists() {
+            true => schema_version(&Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY,
+            )?)?,
+            false => 0,
+        };
+        check_version(version, MIGRATIONS.len())?;
+
+        Ok(MIGRATIONS
+            .iter()
+            .enumerate()
+            .skip(version)
+            .map(|(i, (description, _))| format!("version {} ({})", i + 1, description))
+            .collect())
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    // a query can sit out the other server's write for up to `BUSY_TIMEOUT`,
+    // which would stall everything else on a current_thread runtime if run inline
+    fn run<T, F>(&self, query: F) -> RepoFuture<'static, T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || query(&mut conn.lock().unwrap())).await?
+        })
+    }
+}
+
+fn schema_version(conn: &Connection) -> Result<usize> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    check_version(schema_version(conn)?, MIGRATIONS.len())?;
+
+    for (i, (description, sql)) in MIGRATIONS.iter().enumerate() {
+        // takes the write lock before reading the version, so a server starting
+        // alongside can't apply the same step twice
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if schema_version(&tx)? > i {
+            continue;
+        }
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        tracing::info!(
+            "migrated sqlite schema to version {} ({})",
+            i + 1,
+            description
+        );
+    }
+
+    Ok(())
+}
+
+fn register(conn: &mut Connection, account: &AccountDoc) -> Result<()> {
+    let ban_history = serde_json::to_string(&account.ban_history)?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO account (uid, username, password_hash, token, is_banned, ban_reason,
+        ban_start, ban_end, ban_issuer, ban_history)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            account.uid,
+            account.username,
+            account.password_hash,
+            account.token,
+            account.is_banned,
+            account.ban_reason,
+            account.ban_start,
+            account.ban_end,
+            account.ban_issuer,
+            ban_history
+        ],
+    )?;
+    tx.execute(
+        "INSERT INTO srtools (uid, username, data) VALUES (?1, ?2, NULL)",
+        params![account.uid, account.username],
+    )?;
+    tx.execute(
+        "INSERT INTO srtools_meta (username, next_sync_allowed, next_export_allowed)
+        VALUES (?1, 0, 0)",
+        [&account.username],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn account_from_row(row: &Row) -> rusqlite::Result<AccountDoc> {
+    let ban_history: String = row.get("ban_history")?;
+    let ban_history = serde_json::from_str(&ban_history).map_err(|e| {
+        let index = row.as_ref().column_index("ban_history").unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))
+    })?;
+
+    Ok(AccountDoc {
+        uid: row.get("uid")?,
+        username: row.get("username")?,
+        password_hash: row.get("password_hash")?,
+        token: row.get("token")?,
+        is_banned: row.get("is_banned")?,
+        ban_reason: row.get("ban_reason")?,
+        ban_start: row.get("ban_start")?,
+        ban_end: row.get("ban_end")?,
+        ban_issuer: row.get("ban_issuer")?,
+        ban_history,
+    })
+}
+
+// `column` is always one of ours, never user input
+fn fetch_account(conn: &Connection, column: &str, value: impl ToSql) -> Result<Option<AccountDoc>> {
+    let sql = format!("SELECT * FROM account WHERE {} = ?1", column);
+    Ok(conn.query_row(&sql, [value], account_from_row).optional()?)
+}
+
+fn list_accounts(
+    conn: &Connection,
+    search: Option<&str>,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<AccountDoc>> {
+    // LIKE already ignores case, `\` keeps `%` and `_` in the search literal
+    let pattern = search.map(|v| {
+        let escaped = v
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("%{}%", escaped)
+    });
+    let mut stmt = conn.prepare(
+        "SELECT * FROM account WHERE ?1 IS NULL OR username LIKE ?1 ESCAPE '\\'
+        ORDER BY uid LIMIT ?2 OFFSET ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            pattern,
+            i64::try_from(limit).unwrap_or(i64::MAX),
+            i64::try_from(offset).unwrap_or(i64::MAX)
+        ],
+        account_from_row,
+    )?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn delete_account(conn: &mut Connection, uid: u32) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM srtools_meta WHERE username = (SELECT username FROM account WHERE uid = ?1)",
+        [uid],
+    )?;
+    tx.execute("DELETE FROM srtools WHERE uid = ?1", [uid])?;
+    tx.execute("DELETE FROM account WHERE uid = ?1", [uid])?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn fetch_srtools(conn: &Connection, column: &str, value: impl ToSql) -> Result<Option<SRToolsDoc>> {
+    let sql = format!(
+        "SELECT uid, username, data FROM srtools WHERE {} = ?1",
+        column
+    );
+    let row = conn
+        .query_row(&sql, [value], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?))
+        })
+        .optional()?;
+
+    let Some((uid, username, data)) = row else {
+        return Ok(None);
+    };
+    let data = data.map(|v| serde_json::from_str(&v)).transpose()?;
+
+    Ok(Some(SRToolsDoc {
+        uid,
+        username,
+        data,
+    }))
+}
+
+impl AccountRepo for SqliteStorage {
+    fn register<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        let account = account.clone();
+        self.run(move |conn| register(conn, &account))
+    }
+
+    fn username_taken<'a>(&'a self, username: &'a str) -> RepoFuture<'a, bool> {
+        let username = username.to_string();
+        self.run(move |conn| fetch_account(conn, "username", username).map(|v| v.is_some()))
+    }
+
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<AccountDoc>> {
+        self.run(move |conn| fetch_account(conn, "uid", uid))
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<AccountDoc>> {
+        let username = username.to_string();
+        self.run(move |conn| fetch_account(conn, "username", username))
+    }
+
+    fn update_token<'a>(&'a self, uid: u32, token: &'a str) -> RepoFuture<'a, ()> {
+        let token = token.to_string();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE account SET token = ?1 WHERE uid = ?2",
+                params![token, uid],
+            )?;
+            Ok(())
+        })
+    }
+
+    // same as the mongo backend, the stored value is the uid after the one handed out
+    fn next_uid(&self) -> RepoFuture<'_, u32> {
+        self.run(|conn| {
+            Ok(conn.query_row(
+                "INSERT INTO account_meta (id, next_uid) VALUES ('meta', ?1)
+                ON CONFLICT (id) DO UPDATE SET next_uid = next_uid + 1
+                RETURNING next_uid - 1",
+                [STARTING_UID + 1],
+                |row| row.get(0),
+            )?)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        search: Option<&'a str>,
+        offset: u64,
+        limit: u64,
+    ) -> RepoFuture<'a, Vec<AccountDoc>> {
+        let search = search.map(str::to_string);
+        self.run(move |conn| list_accounts(conn, search.as_deref(), offset, limit))
+    }
+
+    fn update_ban<'a>(&'a self, account: &'a AccountDoc) -> RepoFuture<'a, ()> {
+        let account = account.clone();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE account SET is_banned = ?1, ban_reason = ?2, ban_start = ?3, ban_end = ?4,
+                ban_issuer = ?5, ban_history = ?6 WHERE uid = ?7",
+                params![
+                    account.is_banned,
+                    account.ban_reason,
+                    account.ban_start,
+                    account.ban_end,
+                    account.ban_issuer,
+                    serde_json::to_string(&account.ban_history)?,
+                    account.uid
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn update_password_hash<'a>(&'a self, uid: u32, hash: &'a str) -> RepoFuture<'a, ()> {
+        let hash = hash.to_string();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE account SET password_hash = ?1 WHERE uid = ?2",
+                params![hash, uid],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn delete(&self, uid: u32) -> RepoFuture<'_, ()> {
+        self.run(move |conn| delete_account(conn, uid))
+    }
+}
+
+impl HotfixRepo for SqliteStorage {
+    fn fetch_by_version<'a>(&'a self, version: &'a str) -> RepoFuture<'a, Option<HotfixDoc>> {
+        let version = version.to_string();
+        self.run(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT * FROM hotfix WHERE version = ?1",
+                    [version],
+                    |row| {
+                        Ok(HotfixDoc {
+                            version: row.get("version")?,
+                            ifix_url: row.get("ifix_url")?,
+                            ifix_version: row.get("ifix_version")?,
+                            mdk_res_url: row.get("mdk_res_url")?,
+                            mdk_res_version: row.get("mdk_res_version")?,
+                            asset_bundle_url: row.get("asset_bundle_url")?,
+                            ex_resource_url: row.get("ex_resource_url")?,
+                            fetched_at: row.get("fetched_at")?,
+                        })
+                    },
+                )
+                .optional()?)
+        })
+    }
+
+    fn upsert<'a>(&'a self, hotfix: &'a HotfixDoc) -> RepoFuture<'a, ()> {
+        let hotfix = hotfix.clone();
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO hotfix (version, ifix_url, ifix_version, mdk_res_url,
+                mdk_res_version, asset_bundle_url, ex_resource_url, fetched_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    hotfix.version,
+                    hotfix.ifix_url,
+                    hotfix.ifix_version,
+                    hotfix.mdk_res_url,
+                    hotfix.mdk_res_version,
+                    hotfix.asset_bundle_url,
+                    hotfix.ex_resource_url,
+                    hotfix.fetched_at
+                ],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+impl SRToolsRepo for SqliteStorage {
+    fn fetch_by_uid(&self, uid: u32) -> RepoFuture<'_, Option<SRToolsDoc>> {
+        self.run(move |conn| fetch_srtools(conn, "uid", uid))
+    }
+
+    fn fetch_by_username<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsDoc>> {
+        let username = username.to_string();
+        self.run(move |conn| fetch_srtools(conn, "username", username))
+    }
+
+    fn set_data_by_username<'a>(
+        &'a self,
+        username: &'a str,
+        data: &'a SRToolsData,
+    ) -> RepoFuture<'a, ()> {
+        let data = match serde_json::to_string(data) {
+            Ok(v) => v,
+            Err(e) => return ready(Err(e.into())),
+        };
+        let username = username.to_string();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE srtools SET data = ?1 WHERE username = ?2",
+                params![data, username],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn fetch_meta<'a>(&'a self, username: &'a str) -> RepoFuture<'a, Option<SRToolsMetaDoc>> {
+        let username = username.to_string();
+        self.run(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT * FROM srtools_meta WHERE username = ?1",
+                    [username],
+                    |row| {
+                        Ok(SRToolsMetaDoc {
+                            username: row.get("username")?,
+                            next_sync_allowed: row.get("next_sync_allowed")?,
+                            next_export_allowed: row.get("next_export_allowed")?,
+                        })
+                    },
+                )
+                .optional()?)
+        })
+    }
+
+    fn update_next_sync<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        let username = username.to_string();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE srtools_meta SET next_sync_allowed = ?1 WHERE username = ?2",
+                params![cooldown_end(SRTOOLS_SYNC_COOLDOWN_MINUTES), username],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn update_next_export<'a>(&'a self, username: &'a str) -> RepoFuture<'a, ()> {
+        let username = username.to_string();
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE srtools_meta SET next_export_allowed = ?1 WHERE username = ?2",
+                params![cooldown_end(SRTOOLS_EXPORT_COOLDOWN_MINUTES), username],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::repo::Storage;
+    use std::collections::HashMap;
+
+    fn storage() -> Storage {
+        Storage::new(SqliteStorage::open_in_memory().unwrap())
+    }
+
+    fn account(uid: u32, username: &str) -> AccountDoc {
+        AccountDoc::new(uid, username, String::from("hash"), String::from("token"))
+    }
+
+    #[test]
+    fn migrations_are_only_applied_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn newer_schemas_are_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        let Err(Error::SchemaTooNew { .. }) = migrate(&mut conn) else {
+            panic!("expected a too new schema");
+        };
+    }
+
+    #[tokio::test]
+    async fn uids_count_up_from_the_starting_uid() {
+        let storage = storage();
+        assert_eq!(storage.accounts.next_uid().await.unwrap(), STARTING_UID);
+        assert_eq!(storage.accounts.next_uid().await.unwrap(), STARTING_UID + 1);
+    }
+
+    #[tokio::test]
+    async fn accounts_round_trip() {
+        let storage = storage();
+        let mut account = account(1, "kiana");
+        account.is_banned = true;
+        account.ban_reason = Some(String::from("cheating"));
+        storage.accounts.register(&account).await.unwrap();
+        storage.accounts.update_token(1, "new").await.unwrap();
+
+        let fetched = storage.accounts.fetch_by_username("kiana").await.unwrap();
+        let fetched = fetched.unwrap();
+        assert_eq!(fetched.token, "new");
+        assert!(fetched.is_banned);
+        assert_eq!(fetched.ban_reason.as_deref(), Some("cheating"));
+
+        let Err(Error::DuplicateKey(_)) = storage.accounts.register(&account).await else {
+            panic!("expected a duplicate key");
+        };
+    }
+
+    #[tokio::test]
+    async fn bans_and_their_history_round_trip() {
+        let storage = storage();
+        let mut account = account(1, "kiana");
+        storage.accounts.register(&account).await.unwrap();
+
+        account.ban("cheating", "admin", 10, Some(100));
+        storage.accounts.update_ban(&account).await.unwrap();
+        let fetched = storage.accounts.fetch_by_uid(1).await.unwrap().unwrap();
+        assert_eq!(fetched.ban_start, Some(10));
+        assert_eq!(fetched.ban_end, Some(100));
+        assert_eq!(fetched.ban_issuer.as_deref(), Some("admin"));
+
+        account.unban(50);
+        storage.accounts.update_ban(&account).await.unwrap();
+        let fetched = storage.accounts.fetch_by_uid(1).await.unwrap().unwrap();
+        assert!(!fetched.is_banned);
+        assert!(fetched.ban_reason.is_none());
+        assert_eq!(fetched.ban_history, account.ban_history);
+        assert_eq!(fetched.ban_history[0].lifted_at, Some(50));
+    }
+
+    #[tokio::test]
+    async fn list_treats_wildcards_literally() {
+        let storage = storage();
+        for (uid, username) in [(1, "kiana_k"), (2, "kianak"), (3, "MEI")] {
+            storage
+                .accounts
+                .register(&account(uid, username))
+                .await
+                .unwrap();
+        }
+
+        let page = storage.accounts.list(Some("a_k"), 0, 10).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].uid, 1);
+        let page = storage.accounts.list(Some("mei"), 0, 10).await.unwrap();
+        assert_eq!(page[0].uid, 3);
+        assert_eq!(storage.accounts.list(None, 1, 10).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn delete_removes_the_srtools_docs() {
+        let storage = storage();
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        storage.accounts.delete(1).await.unwrap();
+
+        assert!(storage.accounts.fetch_by_uid(1).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_by_uid(1).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_meta("kiana").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn register_is_all_or_nothing() {
+        let storage = storage();
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        assert!(storage.srtools.fetch_meta("kiana").await.unwrap().is_some());
+
+        let Err(Error::DuplicateKey(_)) = storage.accounts.register(&account(2, "kiana")).await
+        else {
+            panic!("expected a duplicate key");
+        };
+        assert!(storage.accounts.fetch_by_uid(2).await.unwrap().is_none());
+        assert!(storage.srtools.fetch_by_uid(2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn hotfixes_keep_their_fetch_time() {
+        let storage = storage();
+        let mut hotfix = HotfixDoc::empty("OSPROD3.3.0");
+        hotfix.fetched_at = 1700000000;
+        storage.hotfixes.upsert(&hotfix).await.unwrap();
+
+        let fetched = storage.hotfixes.fetch_by_version("OSPROD3.3.0").await;
+        assert_eq!(fetched.unwrap().unwrap().fetched_at, 1700000000);
+    }
+
+    #[tokio::test]
+    async fn srtools_data_is_stored_as_json() {
+        let storage = storage();
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        assert!(
+            storage
+                .srtools
+                .fetch_by_uid(1)
+                .await
+                .unwrap()
+                .unwrap()
+                .data
+                .is_none()
+        );
+
+        let data = SRToolsData {
+            avatars: HashMap::new(),
+            relics: Vec::new(),
+            lightcones: Vec::new(),
+            battle_config: serde_json::from_str(
+                r#"{"battle_type":"DU","cycle_count":30,"stage_id":1,"path_resonance_id":0,"monsters":[]}"#,
+            )
+            .unwrap(),
+        };
+        storage
+            .srtools
+            .set_data_by_username("kiana", &data)
+            .await
+            .unwrap();
+
+        let fetched = storage.srtools.fetch_by_username("kiana").await.unwrap();
+        let fetched = fetched.unwrap().data.unwrap();
+        assert_eq!(fetched.battle_config.cycle_count, 30);
+    }
+}
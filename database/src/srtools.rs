@@ -1,16 +1,30 @@
+use common::proto;
 use common::time::get_duration_since_unix;
 use mongodb::{
-    Collection, Database,
+    Collection, Database, IndexModel,
     bson::{self, doc},
     error::Result,
+    options::IndexOptions,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 const SRTOOLS_COLL_NAME: &str = "srtools";
 const SRTOOLS_META_COLL_NAME: &str = "srtools_meta";
-const SRTOOLS_SYNC_COOLDOWN_MINUTES: u64 = 30;
-const SRTOOLS_EXPORT_COOLDOWN_MINUTES: u64 = 15;
+pub(crate) const SRTOOLS_SYNC_COOLDOWN_MINUTES: u64 = 30;
+pub(crate) const SRTOOLS_EXPORT_COOLDOWN_MINUTES: u64 = 15;
+
+// srtools numbers relics and lightcones separately, both starting from 0.
+// the client wants non-zero unique ids that don't collide across the bag,
+// so each kind gets its own range, tagged in the bits above the mask
+const UNIQUE_ID_MASK: u32 = (1 << 30) - 1;
+const RELIC_UNIQUE_ID_TAG: u32 = 0;
+const LIGHTCONE_UNIQUE_ID_TAG: u32 = 1 << 30;
+
+const LINEUP_MAX_AVATARS: usize = 4;
+const AVATAR_FULL_HP: u32 = 10000;
+// as many as fit on the field at once
+const WAVE_MAX_MONSTERS: u32 = 5;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SRToolsData {
@@ -40,15 +54,41 @@ pub struct AvatarData {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BattleConfig {
+    // which srtools editor tab made the config (DEFAULT, MOC, PF, AS). it's not
+    // read, `stage_id` already decides what kind of fight the client sets up
     pub battle_type: String,
-    // pub blessings: Vec<Option<serde_json::Value>>,
-    // pub custom_stats: Vec<Option<serde_json::Value>>,
+    #[serde(default)]
+    pub blessings: Vec<Blessing>,
+    #[serde(default)]
+    pub custom_stats: Vec<CustomStat>,
     pub cycle_count: u32,
     pub stage_id: u32,
     pub path_resonance_id: u32,
     pub monsters: Vec<Vec<Monster>>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Blessing {
+    pub id: u32,
+    pub level: u32,
+    #[serde(default)]
+    pub dynamic_key: Option<DynamicKey>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DynamicKey {
+    pub key: String,
+    pub value: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CustomStat {
+    pub avatar_id: u32,
+    pub sub_affix_id: u32,
+    pub count: u32,
+    pub step: u32,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Monster {
     pub amount: u32,
@@ -84,6 +124,332 @@ pub struct SubAffix {
     pub sub_affix_id: u32,
 }
 
+impl SRToolsData {
+    // checks what the rest of this file trusts, before a sync gets stored
+    pub fn validate(&self) -> std::result::Result<(), &'static str> {
+        let mut unique_ids = HashSet::new();
+        let relic_ids = self.relics.iter().map(|v| v.unique_id());
+        let lightcone_ids = self.lightcones.iter().map(|v| v.unique_id());
+        for unique_id in relic_ids.chain(lightcone_ids) {
+            match unique_id {
+                Some(v) if unique_ids.insert(v) => {}
+                Some(_) => return Err("Two relics or lightcones share an internal_uid."),
+                None => return Err("A relic or lightcone internal_uid is too large."),
+            }
+        }
+        self.battle_config.validate()
+    }
+
+    // sorted by avatar id so the list (and the default lineup) is stable between logins
+    fn sorted_avatars(&self) -> Vec<&Avatar> {
+        let mut avatars = self.avatars.values().collect::<Vec<&Avatar>>();
+        avatars.sort_by_key(|v| v.avatar_id);
+        avatars
+    }
+
+    // srtools has no notion of lineups, so the first four synced avatars make up the team
+    pub fn lineup_avatars(&self) -> Vec<&Avatar> {
+        let mut avatars = self.sorted_avatars();
+        avatars.truncate(LINEUP_MAX_AVATARS);
+        avatars
+    }
+
+    pub fn avatar_list(&self) -> Vec<proto::Avatar> {
+        self.sorted_avatars()
+            .into_iter()
+            .map(|avatar| {
+                let mut proto_avatar = proto::Avatar::from(avatar);
+                if let Some(lightcone) = self
+                    .lightcones
+                    .iter()
+                    .find(|v| v.equip_avatar == avatar.avatar_id)
+                {
+                    proto_avatar.equipment_unique_id = lightcone.unique_id().unwrap_or_default();
+                }
+                proto_avatar.equip_relic_list = self
+                    .relics
+                    .iter()
+                    .filter(|v| v.equip_avatar == avatar.avatar_id)
+                    .map(|v| proto::EquipRelic {
+                        relic_unique_id: v.unique_id().unwrap_or_default(),
+                        r#type: v.slot(),
+                    })
+                    .collect();
+                proto_avatar
+            })
+            .collect()
+    }
+
+    pub fn relic_list(&self) -> Vec<proto::Relic> {
+        self.relics.iter().map(proto::Relic::from).collect()
+    }
+
+    pub fn equipment_list(&self) -> Vec<proto::Equipment> {
+        self.lightcones.iter().map(proto::Equipment::from).collect()
+    }
+
+    pub fn battle_avatar_list(&self) -> Vec<proto::BattleAvatar> {
+        self.lineup_avatars()
+            .into_iter()
+            .enumerate()
+            .map(|(index, avatar)| proto::BattleAvatar {
+                id: avatar.avatar_id,
+                index: index as u32,
+                level: avatar.level,
+                promotion: avatar.promotion,
+                rank: avatar.data.rank,
+                hp: AVATAR_FULL_HP,
+                avatar_type: proto::AvatarType::AvatarFormalType as i32,
+                skilltree_list: proto::Avatar::from(avatar).skilltree_list,
+                sp_bar: Some(proto::SpBarInfo {
+                    cur_sp: avatar.sp_value,
+                    max_sp: avatar.sp_max,
+                }),
+                equipment_list: self
+                    .lightcones
+                    .iter()
+                    .filter(|v| v.equip_avatar == avatar.avatar_id)
+                    .map(proto::BattleEquipment::from)
+                    .collect(),
+                relic_list: self.battle_relic_list(avatar.avatar_id),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // custom stats ride along as extra sub affixes on the avatar's first relic,
+    // an avatar with nothing equipped has nowhere to put them and goes without
+    fn battle_relic_list(&self, avatar_id: u32) -> Vec<proto::BattleRelic> {
+        let mut relic_list = self
+            .relics
+            .iter()
+            .filter(|v| v.equip_avatar == avatar_id)
+            .map(proto::BattleRelic::from)
+            .collect::<Vec<proto::BattleRelic>>();
+
+        let custom_stats = self
+            .battle_config
+            .custom_stats
+            .iter()
+            .filter(|v| v.avatar_id == avatar_id)
+            .map(proto::RelicAffix::from)
+            .collect::<Vec<proto::RelicAffix>>();
+
+        match relic_list.first_mut() {
+            Some(relic) => relic.sub_affix_list.extend(custom_stats),
+            None if !custom_stats.is_empty() => {
+                tracing::warn!(
+                    "Skipping custom stats of avatar {}, it has no relic to carry them",
+                    avatar_id
+                );
+            }
+            None => {}
+        }
+
+        relic_list
+    }
+}
+
+impl BattleConfig {
+    pub fn buff_list(&self) -> Vec<proto::BattleBuff> {
+        self.blessings.iter().map(proto::BattleBuff::from).collect()
+    }
+
+    // each srtools wave lists monster kinds with an amount, the client wants one entry
+    // per monster. the client only takes a level per wave, so a srtools wave mixing
+    // levels goes out at the highest of them to keep the configured wave count
+    pub fn monster_wave_list(&self) -> Vec<proto::SceneMonsterWave> {
+        self.monsters
+            .iter()
+            .enumerate()
+            .map(|(index, wave)| proto::SceneMonsterWave {
+                battle_wave_id: index as u32 + 1,
+                battle_stage_id: self.stage_id,
+                monster_list: wave
+                    .iter()
+                    .flat_map(|v| {
+                        std::iter::repeat_n(
+                            proto::SceneMonster {
+                                monster_id: v.monster_id,
+                                ..Default::default()
+                            },
+                            v.amount.min(WAVE_MAX_MONSTERS) as usize,
+                        )
+                    })
+                    .take(WAVE_MAX_MONSTERS as usize)
+                    .collect(),
+                monster_param: Some(proto::SceneMonsterWaveParam {
+                    level: wave.iter().map(|v| v.level).max().unwrap_or_default(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> std::result::Result<(), &'static str> {
+        for wave in &self.monsters {
+            let amount = wave
+                .iter()
+                .try_fold(0u32, |total, v| total.checked_add(v.amount));
+            if amount.is_none_or(|v| v > WAVE_MAX_MONSTERS) {
+                return Err("A wave has more than 5 monsters.");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&Avatar> for proto::Avatar {
+    fn from(value: &Avatar) -> Self {
+        Self {
+            base_avatar_id: value.avatar_id,
+            level: value.level,
+            promotion: value.promotion,
+            rank: value.data.rank,
+            skilltree_list: value
+                .data
+                .skills
+                .iter()
+                .filter_map(|(point_id, level)| {
+                    Some(proto::AvatarSkillTree {
+                        point_id: point_id.parse().ok()?,
+                        level: *level,
+                    })
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+// none once `internal_uid` runs out of its kind's range, which a sync rejects
+fn unique_id(tag: u32, internal_uid: u32) -> Option<u32> {
+    internal_uid
+        .checked_add(1)
+        .filter(|v| *v <= UNIQUE_ID_MASK)
+        .map(|v| v | tag)
+}
+
+impl Lightcone {
+    pub fn unique_id(&self) -> Option<u32> {
+        unique_id(LIGHTCONE_UNIQUE_ID_TAG, self.internal_uid)
+    }
+}
+
+impl From<&Lightcone> for proto::Equipment {
+    fn from(value: &Lightcone) -> Self {
+        Self {
+            unique_id: value.unique_id().unwrap_or_default(),
+            tid: value.item_id,
+            level: value.level,
+            promotion: value.promotion,
+            rank: value.rank,
+            dress_avatar_id: value.equip_avatar,
+            is_protected: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&Lightcone> for proto::BattleEquipment {
+    fn from(value: &Lightcone) -> Self {
+        Self {
+            id: value.item_id,
+            level: value.level,
+            promotion: value.promotion,
+            rank: value.rank,
+        }
+    }
+}
+
+impl Relic {
+    pub fn unique_id(&self) -> Option<u32> {
+        unique_id(RELIC_UNIQUE_ID_TAG, self.internal_uid)
+    }
+
+    // the last digit of a relic id is its slot, e.g. 61011 is a head piece
+    pub fn slot(&self) -> u32 {
+        self.relic_id % 10
+    }
+}
+
+impl From<&Relic> for proto::Relic {
+    fn from(value: &Relic) -> Self {
+        Self {
+            unique_id: value.unique_id().unwrap_or_default(),
+            tid: value.relic_id,
+            level: value.level,
+            main_affix_id: value.main_affix_id,
+            dress_avatar_id: value.equip_avatar,
+            is_protected: true,
+            sub_affix_list: value
+                .sub_affixes
+                .iter()
+                .map(proto::RelicAffix::from)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&Relic> for proto::BattleRelic {
+    fn from(value: &Relic) -> Self {
+        Self {
+            id: value.relic_id,
+            unique_id: value.unique_id().unwrap_or_default(),
+            level: value.level,
+            main_affix_id: value.main_affix_id,
+            set_id: value.relic_set_id,
+            r#type: value.slot(),
+            sub_affix_list: value
+                .sub_affixes
+                .iter()
+                .map(proto::RelicAffix::from)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&SubAffix> for proto::RelicAffix {
+    fn from(value: &SubAffix) -> Self {
+        Self {
+            affix_id: value.sub_affix_id,
+            cnt: value.count,
+            step: value.step,
+        }
+    }
+}
+
+// blessings apply to the whole team on every wave
+impl From<&Blessing> for proto::BattleBuff {
+    fn from(value: &Blessing) -> Self {
+        Self {
+            id: value.id,
+            level: value.level,
+            owner_index: u32::MAX,
+            wave_flag: u32::MAX,
+            dynamic_values: value
+                .dynamic_key
+                .iter()
+                .map(|v| (v.key.clone(), v.value as f32))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&CustomStat> for proto::RelicAffix {
+    fn from(value: &CustomStat) -> Self {
+        Self {
+            affix_id: value.sub_affix_id,
+            cnt: value.count,
+            step: value.step,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SRToolsDoc {
     #[serde(rename = "_id")]
@@ -93,15 +459,40 @@ pub struct SRToolsDoc {
 }
 
 impl SRToolsDoc {
+    // what a new account starts with, nothing synced yet
+    pub fn empty(uid: u32, username: &str) -> Self {
+        Self {
+            uid,
+            username: username.to_string(),
+            data: None,
+        }
+    }
+
     pub fn get_collection(db: &Database) -> Collection<Self> {
         db.collection::<Self>(SRTOOLS_COLL_NAME)
     }
 
+    pub async fn create_indexes(collection: &Collection<Self>) -> Result<()> {
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! { "username": 1 })
+            .options(options)
+            .build();
+        collection.create_index(index).await?;
+        Ok(())
+    }
+
     pub async fn insert_to_collection(&self, collection: &Collection<Self>) -> Result<()> {
         collection.insert_one(self).await?;
         Ok(())
     }
 
+    pub async fn delete_by_uid(collection: &Collection<Self>, uid: u32) -> Result<()> {
+        let filter = doc! { "_id": uid };
+        collection.delete_one(filter).await?;
+        Ok(())
+    }
+
     pub async fn fetch_by_uid(collection: &Collection<Self>, uid: u32) -> Result<Option<Self>> {
         let filter = doc! { "_id": uid };
         let result = collection.find_one(filter).await?;
@@ -151,6 +542,14 @@ pub struct SRToolsMetaDoc {
 }
 
 impl SRToolsMetaDoc {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            next_sync_allowed: 0,
+            next_export_allowed: 0,
+        }
+    }
+
     pub fn get_collection(db: &Database) -> Collection<Self> {
         db.collection::<Self>(SRTOOLS_META_COLL_NAME)
     }
@@ -160,6 +559,12 @@ impl SRToolsMetaDoc {
         Ok(())
     }
 
+    pub async fn delete_by_username(collection: &Collection<Self>, username: &str) -> Result<()> {
+        let filter = doc! { "_id": username };
+        collection.delete_one(filter).await?;
+        Ok(())
+    }
+
     pub async fn fetch_by_username(
         collection: &Collection<Self>,
         username: &str,
@@ -173,8 +578,7 @@ impl SRToolsMetaDoc {
         collection: &Collection<Self>,
         username: &str,
     ) -> Result<()> {
-        let next_time = (get_duration_since_unix().as_secs() / 60) + SRTOOLS_SYNC_COOLDOWN_MINUTES;
-        let next_time = next_time as u32;
+        let next_time = cooldown_end(SRTOOLS_SYNC_COOLDOWN_MINUTES);
         let filter = doc! { "_id": username };
         let update = doc! { "$set": { "next_sync_allowed": next_time } };
         collection.update_one(filter, update).await?;
@@ -185,12 +589,239 @@ impl SRToolsMetaDoc {
         collection: &Collection<Self>,
         username: &str,
     ) -> Result<()> {
-        let next_time =
-            (get_duration_since_unix().as_secs() / 60) + SRTOOLS_EXPORT_COOLDOWN_MINUTES;
-        let next_time = next_time as u32;
+        let next_time = cooldown_end(SRTOOLS_EXPORT_COOLDOWN_MINUTES);
         let filter = doc! { "_id": username };
         let update = doc! { "$set": { "next_export_allowed": next_time } };
         collection.update_one(filter, update).await?;
         Ok(())
     }
 }
+
+// in minutes since the unix epoch, same as `next_sync_allowed` and `next_export_allowed`
+pub(crate) fn cooldown_end(cooldown_minutes: u64) -> u32 {
+    ((get_duration_since_unix().as_secs() / 60) + cooldown_minutes) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battle_config(monsters: Vec<Vec<Monster>>) -> BattleConfig {
+        BattleConfig {
+            battle_type: String::from("DEFAULT"),
+            blessings: Vec::new(),
+            custom_stats: Vec::new(),
+            cycle_count: 30,
+            stage_id: 201012311,
+            path_resonance_id: 0,
+            monsters,
+        }
+    }
+
+    fn srtools_data(relic_uids: &[u32], lightcone_uids: &[u32]) -> SRToolsData {
+        SRToolsData {
+            avatars: HashMap::from([(
+                String::from("1310"),
+                Avatar {
+                    avatar_id: 1310,
+                    data: AvatarData {
+                        rank: 0,
+                        skills: HashMap::new(),
+                    },
+                    level: 80,
+                    promotion: 6,
+                    sp_max: 10000,
+                    sp_value: 5000,
+                    techniques: Vec::new(),
+                    owner_uid: 1,
+                },
+            )]),
+            relics: relic_uids
+                .iter()
+                .map(|v| Relic {
+                    equip_avatar: 1310,
+                    internal_uid: *v,
+                    level: 15,
+                    main_affix_id: 1,
+                    relic_id: 61011 + *v % 4,
+                    relic_set_id: 101,
+                    sub_affixes: Vec::new(),
+                })
+                .collect(),
+            lightcones: lightcone_uids
+                .iter()
+                .map(|v| Lightcone {
+                    equip_avatar: 1310,
+                    internal_uid: *v,
+                    item_id: 23024,
+                    level: 80,
+                    promotion: 6,
+                    rank: 1,
+                })
+                .collect(),
+            battle_config: battle_config(Vec::new()),
+        }
+    }
+
+    fn monster(monster_id: u32, level: u32, amount: u32) -> Monster {
+        Monster {
+            amount,
+            level,
+            monster_id,
+        }
+    }
+
+    #[test]
+    fn waves_expand_amounts_and_take_the_highest_level() {
+        let config = battle_config(vec![
+            vec![monster(3013010, 95, 2), monster(3013020, 80, 1)],
+            vec![monster(8003010, 80, 1)],
+        ]);
+
+        let waves = config.monster_wave_list();
+        let summary = waves
+            .iter()
+            .map(|v| {
+                let ids = v
+                    .monster_list
+                    .iter()
+                    .map(|v| v.monster_id)
+                    .collect::<Vec<u32>>();
+                (v.battle_wave_id, v.monster_param.unwrap().level, ids)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 95, vec![3013010, 3013010, 3013020]),
+                (2, 80, vec![8003010]),
+            ]
+        );
+        assert!(waves.iter().all(|v| v.battle_stage_id == 201012311));
+    }
+
+    #[test]
+    fn oversized_waves_are_rejected_and_capped() {
+        let config = battle_config(vec![vec![monster(3013010, 95, u32::MAX)]]);
+        assert!(config.validate().is_err());
+        assert_eq!(config.monster_wave_list()[0].monster_list.len(), 5);
+
+        let config = battle_config(vec![vec![monster(3013010, 95, 3), monster(3013020, 95, 3)]]);
+        assert!(config.validate().is_err());
+        assert_eq!(config.monster_wave_list()[0].monster_list.len(), 5);
+
+        let config = battle_config(vec![vec![monster(3013010, 95, 5)]]);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn relic_and_lightcone_ids_never_collide() {
+        let relic_uids = (0..2500).collect::<Vec<u32>>();
+        let data = srtools_data(&relic_uids, &[0, 1999]);
+        assert!(data.validate().is_ok());
+
+        let relic_ids = data.relic_list().into_iter().map(|v| v.unique_id);
+        let equipment_ids = data.equipment_list().into_iter().map(|v| v.unique_id);
+        let ids = relic_ids.chain(equipment_ids).collect::<Vec<u32>>();
+        assert!(!ids.contains(&0));
+        assert_eq!(ids.iter().collect::<HashSet<&u32>>().len(), ids.len());
+        assert_eq!(ids[0], 1);
+        assert_eq!(ids[2500], LIGHTCONE_UNIQUE_ID_TAG | 1);
+    }
+
+    #[test]
+    fn out_of_range_or_repeated_uids_are_rejected() {
+        assert!(srtools_data(&[u32::MAX], &[]).validate().is_err());
+        assert!(srtools_data(&[], &[UNIQUE_ID_MASK]).validate().is_err());
+        assert!(srtools_data(&[UNIQUE_ID_MASK - 1], &[]).validate().is_ok());
+        assert!(srtools_data(&[3, 3], &[]).validate().is_err());
+        assert_eq!(srtools_data(&[u32::MAX], &[]).relic_list()[0].unique_id, 0);
+    }
+
+    #[test]
+    fn blessings_become_team_wide_buffs() {
+        let mut config = battle_config(Vec::new());
+        config.blessings = vec![
+            Blessing {
+                id: 61104,
+                level: 2,
+                dynamic_key: Some(DynamicKey {
+                    key: String::from("SkillIndex"),
+                    value: 3,
+                }),
+            },
+            Blessing {
+                id: 30112,
+                level: 1,
+                dynamic_key: None,
+            },
+        ];
+
+        let buffs = config.buff_list();
+        assert_eq!(buffs.len(), 2);
+        assert_eq!((buffs[0].id, buffs[0].level), (61104, 2));
+        assert_eq!(
+            buffs[0].dynamic_values,
+            HashMap::from([(String::from("SkillIndex"), 3.0)])
+        );
+        assert_eq!((buffs[1].id, buffs[1].level), (30112, 1));
+        assert!(buffs[1].dynamic_values.is_empty());
+        assert!(
+            buffs
+                .iter()
+                .all(|v| v.owner_index == u32::MAX && v.wave_flag == u32::MAX)
+        );
+    }
+
+    #[test]
+    fn custom_stats_go_on_the_first_relic() {
+        let custom_stat = |avatar_id| CustomStat {
+            avatar_id,
+            sub_affix_id: 7,
+            count: 10,
+            step: 20,
+        };
+        let mut data = srtools_data(&[0, 1], &[]);
+        data.battle_config.custom_stats = vec![custom_stat(1310), custom_stat(1001)];
+
+        let relics = &data.battle_avatar_list()[0].relic_list;
+        let affixes = |i: usize| {
+            relics[i]
+                .sub_affix_list
+                .iter()
+                .map(|v| (v.affix_id, v.cnt, v.step))
+                .collect::<Vec<(u32, u32, u32)>>()
+        };
+        assert_eq!(affixes(0), vec![(7, 10, 20)]);
+        assert!(affixes(1).is_empty());
+
+        // nothing equipped, nothing to carry them
+        let mut data = srtools_data(&[], &[]);
+        data.battle_config.custom_stats = vec![custom_stat(1310)];
+        assert!(data.battle_avatar_list()[0].relic_list.is_empty());
+    }
+
+    #[test]
+    fn avatars_point_at_what_they_equip() {
+        let data = srtools_data(&[0, 1], &[0]);
+        let avatar = &data.avatar_list()[0];
+
+        assert_eq!(
+            avatar.equipment_unique_id,
+            data.equipment_list()[0].unique_id
+        );
+        let equipped = avatar
+            .equip_relic_list
+            .iter()
+            .map(|v| (v.relic_unique_id, v.r#type))
+            .collect::<Vec<(u32, u32)>>();
+        let relics = data
+            .relic_list()
+            .iter()
+            .map(|v| (v.unique_id, v.tid % 10))
+            .collect::<Vec<(u32, u32)>>();
+        assert_eq!(equipped, relics);
+        assert_eq!(equipped, vec![(1, 1), (2, 2)]);
+        assert!(data.relic_list().iter().all(|v| v.dress_avatar_id == 1310));
+    }
+}
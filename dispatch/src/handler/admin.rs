@@ -0,0 +1,336 @@
+use crate::util::admin::admin_auth_middleware;
+use crate::util::password::hash_password;
+use actix_web::{HttpResponse, Responder, delete, get, middleware, post, web};
+use common::time::get_duration_since_unix;
+use common::token;
+use database::Storage;
+use database::account::{AccountDoc, BanRecord};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+const DEFAULT_ISSUER: &str = "admin";
+
+// everything under `/admin`, only registered when an api key is configured
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .wrap(middleware::from_fn(admin_auth_middleware))
+            .service(get_accounts)
+            .service(get_account)
+            .service(post_ban)
+            .service(post_unban)
+            .service(post_reset_password)
+            .service(post_revoke_token)
+            .service(delete_account),
+    );
+}
+
+// an account without its password hash and token
+#[derive(Serialize)]
+struct AccountView {
+    uid: u32,
+    username: String,
+    // false once a timed ban has run out, even though `is_banned` is still set
+    ban_active: bool,
+    is_banned: bool,
+    ban_reason: Option<String>,
+    ban_start: Option<i64>,
+    ban_end: Option<i64>,
+    ban_issuer: Option<String>,
+    ban_history: Vec<BanRecord>,
+}
+
+impl From<AccountDoc> for AccountView {
+    fn from(account: AccountDoc) -> Self {
+        Self {
+            ban_active: account.is_ban_active(now()),
+            uid: account.uid,
+            username: account.username,
+            is_banned: account.is_banned,
+            ban_reason: account.ban_reason,
+            ban_start: account.ban_start,
+            ban_end: account.ban_end,
+            ban_issuer: account.ban_issuer,
+            ban_history: account.ban_history,
+        }
+    }
+}
+
+fn now() -> i64 {
+    get_duration_since_unix().as_secs() as i64
+}
+
+fn internal_error(doing: &str, e: database::Error) -> HttpResponse {
+    tracing::error!("{}: {}", doing, e);
+    HttpResponse::InternalServerError().body("Internal server error.")
+}
+
+// `Err` is the response to send instead
+async fn fetch_account(storage: &Storage, uid: u32) -> Result<AccountDoc, HttpResponse> {
+    match storage.accounts.fetch_by_uid(uid).await {
+        Ok(Some(v)) => Ok(v),
+        Ok(None) => Err(HttpResponse::NotFound().body("Account not found")),
+        Err(e) => Err(internal_error("Fetching account by uid", e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    search: Option<String>,
+    #[serde(default)]
+    offset: u64,
+    limit: Option<u64>,
+}
+
+#[get("/accounts")]
+pub async fn get_accounts(
+    query: web::Query<ListQuery>,
+    storage: web::Data<Storage>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let search = query.search.as_deref().filter(|v| !v.is_empty());
+
+    match storage.accounts.list(search, query.offset, limit).await {
+        Ok(accounts) => {
+            let accounts: Vec<AccountView> = accounts.into_iter().map(Into::into).collect();
+            HttpResponse::Ok().json(accounts)
+        }
+        Err(e) => internal_error("Listing accounts", e),
+    }
+}
+
+#[get("/accounts/{uid}")]
+pub async fn get_account(uid: web::Path<u32>, storage: web::Data<Storage>) -> impl Responder {
+    match fetch_account(&storage, *uid).await {
+        Ok(account) => HttpResponse::Ok().json(AccountView::from(account)),
+        Err(response) => response,
+    }
+}
+
+#[derive(Deserialize)]
+struct BanRequest {
+    reason: String,
+    // left out for a ban that never lifts
+    duration_minutes: Option<u64>,
+    // who to record as having issued it, the api key doesn't say
+    issuer: Option<String>,
+}
+
+#[post("/accounts/{uid}/ban")]
+pub async fn post_ban(
+    uid: web::Path<u32>,
+    request: web::Json<BanRequest>,
+    storage: web::Data<Storage>,
+) -> impl Responder {
+    if request.reason.is_empty() {
+        return HttpResponse::BadRequest().body("Reason must not be empty");
+    }
+    let now = now();
+    let end = match request.duration_minutes {
+        Some(v) => match i64::try_from(v)
+            .ok()
+            .and_then(|v| v.checked_mul(60))
+            .and_then(|v| now.checked_add(v))
+        {
+            Some(end) => Some(end),
+            None => return HttpResponse::BadRequest().body("Duration is too long"),
+        },
+        None => None,
+    };
+    let mut account = match fetch_account(&storage, *uid).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+
+    let issuer = request.issuer.as_deref().unwrap_or(DEFAULT_ISSUER);
+    account.ban(&request.reason, issuer, now, end);
+
+    match storage.accounts.update_ban(&account).await {
+        Ok(()) => {
+            tracing::info!("{} banned uid {}: {}", issuer, uid, request.reason);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => internal_error("Banning account", e),
+    }
+}
+
+#[post("/accounts/{uid}/unban")]
+pub async fn post_unban(uid: web::Path<u32>, storage: web::Data<Storage>) -> impl Responder {
+    let mut account = match fetch_account(&storage, *uid).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+    account.unban(now());
+
+    match storage.accounts.update_ban(&account).await {
+        Ok(()) => {
+            tracing::info!("unbanned uid {}", uid);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => internal_error("Unbanning account", e),
+    }
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest {
+    password: String,
+}
+
+// also revokes the token, so whoever knew the old password is logged out
+#[post("/accounts/{uid}/reset_password")]
+pub async fn post_reset_password(
+    uid: web::Path<u32>,
+    request: web::Json<ResetPasswordRequest>,
+    storage: web::Data<Storage>,
+) -> impl Responder {
+    if request.password.len() < 4 {
+        return HttpResponse::BadRequest().body("Password must be at least 4 characters");
+    }
+    if let Err(response) = fetch_account(&storage, *uid).await {
+        return response;
+    }
+
+    let password_hash = match hash_password(&request.password) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Hashing password: {}", e);
+            return HttpResponse::InternalServerError().body("Internal server error.");
+        }
+    };
+
+    if let Err(e) = storage
+        .accounts
+        .update_password_hash(*uid, &password_hash)
+        .await
+    {
+        return internal_error("Updating password hash", e);
+    }
+    if let Err(e) = storage
+        .accounts
+        .update_token(*uid, &token::generate(*uid))
+        .await
+    {
+        return internal_error("Updating token", e);
+    }
+
+    tracing::info!("reset the password of uid {}", uid);
+    HttpResponse::NoContent().finish()
+}
+
+// swaps in a fresh token nobody has, logging out every client of the account
+#[post("/accounts/{uid}/revoke_token")]
+pub async fn post_revoke_token(uid: web::Path<u32>, storage: web::Data<Storage>) -> impl Responder {
+    if let Err(response) = fetch_account(&storage, *uid).await {
+        return response;
+    }
+
+    match storage
+        .accounts
+        .update_token(*uid, &token::generate(*uid))
+        .await
+    {
+        Ok(()) => {
+            tracing::info!("revoked the token of uid {}", uid);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => internal_error("Updating token", e),
+    }
+}
+
+#[delete("/accounts/{uid}")]
+pub async fn delete_account(uid: web::Path<u32>, storage: web::Data<Storage>) -> impl Responder {
+    let account = match fetch_account(&storage, *uid).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+
+    match storage.accounts.delete(*uid).await {
+        Ok(()) => {
+            tracing::info!("deleted uid {} ({})", uid, account.username);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => internal_error("Deleting account", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+    use database::repo::memory::MemoryStorage;
+
+    fn account(uid: u32, username: &str) -> AccountDoc {
+        AccountDoc::new(uid, username, String::from("hash"), String::from("token"))
+    }
+
+    // the auth middleware reads the global config, so these call the handlers directly
+    #[actix_web::test]
+    async fn accounts_can_be_banned_and_deleted() {
+        token::set_secret(b"test secret".to_vec());
+        let storage = Storage::new(MemoryStorage::default());
+        storage
+            .accounts
+            .register(&account(1, "kiana"))
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(storage.clone()))
+                .service(get_accounts)
+                .service(post_ban)
+                .service(post_revoke_token)
+                .service(delete_account),
+        )
+        .await;
+
+        // would wrap around to a ban that ended in the past
+        let req = test::TestRequest::post()
+            .uri("/accounts/1/ban")
+            .set_json(serde_json::json!({ "reason": "cheating", "duration_minutes": u64::MAX }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+        let fetched = storage.accounts.fetch_by_uid(1).await.unwrap().unwrap();
+        assert!(!fetched.is_banned);
+
+        let req = test::TestRequest::post()
+            .uri("/accounts/1/ban")
+            .set_json(serde_json::json!({ "reason": "cheating", "duration_minutes": 60 }))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NO_CONTENT
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/accounts?search=KIA")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["ban_reason"], "cheating");
+        assert_eq!(body[0]["ban_issuer"], "admin");
+        assert_eq!(body[0]["ban_active"], true);
+        assert!(body[0]["ban_end"].as_i64().unwrap() > 0);
+        assert!(body[0].get("password_hash").is_none());
+        assert!(body[0].get("token").is_none());
+
+        let req = test::TestRequest::post()
+            .uri("/accounts/1/revoke_token")
+            .to_request();
+        test::call_service(&app, req).await;
+        let fetched = storage.accounts.fetch_by_uid(1).await.unwrap().unwrap();
+        assert_ne!(fetched.token, "token");
+
+        let req = test::TestRequest::delete().uri("/accounts/1").to_request();
+        test::call_service(&app, req).await;
+        let req = test::TestRequest::delete().uri("/accounts/1").to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+        assert!(storage.srtools.fetch_by_uid(1).await.unwrap().is_none());
+    }
+}
@@ -1,13 +1,12 @@
 use crate::util::auto_hotfix;
+use crate::util::client_secret_key::ClientSecretKey;
 use actix_web::{Responder, get, web};
+use common::config::{self, DispatchConfig};
 use common::proto::prost::Message;
 use common::proto::{Dispatch, GateServer, RegionInfo};
-use common::server_config::{
-    DISPATCH_ALWAYS_CHECK_HOTFIX, DISPATCH_BIND_TARGET, DISPATCH_ENV_TYPE, DISPATCH_REGION_NAME,
-    GAMESERVER_BIND_TARGET,
-};
+use common::time::get_duration_since_unix;
+use database::Storage;
 use database::hotfix::HotfixDoc;
-use database::{DB_NAME, MongoClient};
 use reqwest::Client;
 use serde::Deserialize;
 
@@ -15,51 +14,89 @@ use serde::Deserialize;
 struct GatewayQuery {
     version: String,
     dispatch_seed: String,
+    region: Option<String>,
 }
 
+// a stored hotfix is served until it's older than `hotfix_ttl_minutes`. a
+// refresh that fails keeps serving it, stamped as fetched so a dead upstream is
+// only asked again once the ttl is up instead of on every query
+async fn cached_hotfix(
+    config: &DispatchConfig,
+    storage: &Storage,
+    reqwest_client: &Client,
+    query: &GatewayQuery,
+) -> HotfixDoc {
+    let cached = match storage.hotfixes.fetch_by_version(&query.version).await {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Fetching HotfixDoc: {}", e);
+            None
+        }
+    };
+
+    let now = get_duration_since_unix().as_secs() as i64;
+    let ttl = config
+        .hotfix_ttl_minutes
+        .saturating_mul(60)
+        .min(i64::MAX as u64) as i64;
+    match cached {
+        Some(v) if config.hotfix_offline || !v.is_stale(now, ttl) => return v,
+        None if config.hotfix_offline => {
+            tracing::warn!("No stored hotfix for {} in offline mode", query.version);
+            return HotfixDoc::empty(&query.version);
+        }
+        _ => {}
+    }
+
+    match auto_hotfix::fetch_hotfix_from_official(
+        reqwest_client,
+        &config.hotfix_upstreams,
+        &query.version,
+        &query.dispatch_seed,
+    )
+    .await
+    {
+        Ok(v) => {
+            if let Err(e) = storage.hotfixes.upsert(&v).await {
+                tracing::error!("Upserting HotfixDoc: {}", e);
+            }
+            v
+        }
+        Err(e) => {
+            tracing::error!("AutoHotfix: {}", e);
+            let hf = HotfixDoc {
+                fetched_at: now,
+                ..cached.unwrap_or_else(|| HotfixDoc::empty(&query.version))
+            };
+            if let Err(e) = storage.hotfixes.upsert(&hf).await {
+                tracing::error!("Upserting HotfixDoc: {}", e);
+            }
+            hf
+        }
+    }
+}
+
+// `client_secret_key` is left empty while `gameserver.xor_enabled` is off
 #[get("/query_gateway")]
 pub async fn get_query_gateway(
     query: web::Query<GatewayQuery>,
     reqwest_client: web::Data<Client>,
-    mongo_client: web::Data<MongoClient>,
+    storage: web::Data<Storage>,
+    client_secret_key: web::Data<Option<ClientSecretKey>>,
 ) -> impl Responder {
-    let db = mongo_client.database(DB_NAME);
-    let hf_coll = HotfixDoc::get_collection(&db);
-    let hf = match HotfixDoc::fetch_by_version(&hf_coll, &query.version).await {
-        Ok(Some(v)) if !DISPATCH_ALWAYS_CHECK_HOTFIX => v,
-        _ => {
-            let hf2 = match auto_hotfix::fetch_hotfix_from_official(
-                &reqwest_client,
-                &query.version,
-                &query.dispatch_seed,
-            )
-            .await
-            {
-                Ok(v) => v,
-                Err(e) => {
-                    tracing::error!("AutoHotfix: {}", e);
-                    HotfixDoc {
-                        version: query.version.clone(),
-                        ifix_url: String::new(),
-                        ifix_version: String::from("0"),
-                        mdk_res_url: String::new(),
-                        mdk_res_version: String::new(),
-                        asset_bundle_url: String::new(),
-                        ex_resource_url: String::new(),
-                    }
-                }
-            };
-            if let Err(e) = hf2.upsert_to_collection(&hf_coll).await {
-                tracing::error!("Upserting HotfixDoc: {}", e);
-            }
-            hf2
-        }
-    };
+    let config = config::get();
+    let region = config.region(query.region.as_deref());
+    let hf = cached_hotfix(&config.dispatch, &storage, &reqwest_client, &query).await;
 
     let gateserver = GateServer {
         use_tcp: true,
-        ip: GAMESERVER_BIND_TARGET.0.to_string(),
-        port: GAMESERVER_BIND_TARGET.1 as u32,
+        ip: region.gameserver_ip.clone(),
+        port: region.gameserver_port as u32,
+        client_secret_key: client_secret_key
+            .get_ref()
+            .as_ref()
+            .map(|v| v.0.clone())
+            .unwrap_or_default(),
 
         lua_url: hf.mdk_res_url,
         ifix_url: hf.ifix_url,
@@ -83,20 +120,109 @@ pub async fn get_query_gateway(
 
 #[get("/query_dispatch")]
 pub async fn get_query_dispatch() -> impl Responder {
+    let config = config::get();
     let dispatch = Dispatch {
-        region_list: vec![RegionInfo {
-            name: DISPATCH_REGION_NAME.to_string(),
-            title: DISPATCH_REGION_NAME.to_string(),
-            env_type: DISPATCH_ENV_TYPE.to_string(),
-            dispatch_url: format!(
-                "https://{}:{}/query_gateway",
-                DISPATCH_BIND_TARGET.0, DISPATCH_BIND_TARGET.1
-            ),
-            ..Default::default()
-        }],
+        region_list: config
+            .dispatch
+            .regions
+            .iter()
+            .map(|v| RegionInfo {
+                name: v.name.clone(),
+                title: v.title.clone(),
+                env_type: v.env_type.clone(),
+                dispatch_url: v.dispatch_url(),
+                ..Default::default()
+            })
+            .collect(),
         ..Default::default()
     }
     .encode_to_vec();
 
     rbase64::encode(&dispatch)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::config::ServerConfig;
+    use database::repo::memory::MemoryStorage;
+
+    // not a known version prefix, so a refresh fails without touching the network
+    const VERSION: &str = "TESTPROD3.3.0";
+
+    fn query() -> GatewayQuery {
+        GatewayQuery {
+            version: String::from(VERSION),
+            dispatch_seed: String::new(),
+            region: None,
+        }
+    }
+
+    fn stored(fetched_at: i64) -> HotfixDoc {
+        HotfixDoc {
+            asset_bundle_url: String::from("https://example.com/asb"),
+            fetched_at,
+            ..HotfixDoc::empty(VERSION)
+        }
+    }
+
+    #[actix_web::test]
+    async fn failed_refresh_keeps_the_stored_hotfix_and_backs_off() {
+        let config = ServerConfig::default().dispatch;
+        let storage = Storage::new(MemoryStorage::default());
+        storage.hotfixes.upsert(&stored(1)).await.unwrap();
+
+        let hf = cached_hotfix(&config, &storage, &Client::new(), &query()).await;
+        assert_eq!(hf.asset_bundle_url, "https://example.com/asb");
+        let kept = storage.hotfixes.fetch_by_version(VERSION).await.unwrap();
+        let kept = kept.unwrap();
+        assert_eq!(kept.asset_bundle_url, "https://example.com/asb");
+        assert!(!kept.is_stale(get_duration_since_unix().as_secs() as i64, 60));
+    }
+
+    #[actix_web::test]
+    async fn huge_ttl_never_goes_stale() {
+        let mut config = ServerConfig::default().dispatch;
+        config.hotfix_ttl_minutes = u64::MAX;
+        let storage = Storage::new(MemoryStorage::default());
+        storage.hotfixes.upsert(&stored(1)).await.unwrap();
+
+        let hf = cached_hotfix(&config, &storage, &Client::new(), &query()).await;
+        assert_eq!(hf.fetched_at, 1);
+        let kept = storage.hotfixes.fetch_by_version(VERSION).await.unwrap();
+        assert_eq!(kept.unwrap().fetched_at, 1);
+    }
+
+    #[actix_web::test]
+    async fn failed_first_fetch_stores_an_empty_hotfix() {
+        let config = ServerConfig::default().dispatch;
+        let storage = Storage::new(MemoryStorage::default());
+
+        let hf = cached_hotfix(&config, &storage, &Client::new(), &query()).await;
+        assert!(hf.asset_bundle_url.is_empty());
+        let stored = storage.hotfixes.fetch_by_version(VERSION).await.unwrap();
+        assert_eq!(stored.unwrap().fetched_at, hf.fetched_at);
+    }
+
+    #[actix_web::test]
+    async fn offline_mode_serves_stale_hotfixes_and_stores_nothing() {
+        let mut config = ServerConfig::default().dispatch;
+        config.hotfix_offline = true;
+        let storage = Storage::new(MemoryStorage::default());
+
+        let hf = cached_hotfix(&config, &storage, &Client::new(), &query()).await;
+        assert!(hf.asset_bundle_url.is_empty());
+        assert!(
+            storage
+                .hotfixes
+                .fetch_by_version(VERSION)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        storage.hotfixes.upsert(&stored(0)).await.unwrap();
+        let hf = cached_hotfix(&config, &storage, &Client::new(), &query()).await;
+        assert_eq!(hf.asset_bundle_url, "https://example.com/asb");
+    }
+}
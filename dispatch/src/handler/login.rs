@@ -1,57 +1,144 @@
-use crate::util::token::{generate_token, should_refresh_token};
-use actix_web::{Responder, post, web};
+use crate::util::login_key::LoginKey;
+use crate::util::password::verify_password;
+use crate::util::sdk_response::{Empty, GranterLoginData, Retcode, SdkResponse, ShieldLoginData};
+use actix_web::{HttpResponse, post, web};
+use common::time::get_duration_since_unix;
+use common::token::{self, TokenError};
+use database::Storage;
 use database::account::AccountDoc;
-use database::{DB_NAME, MongoClient};
 use serde::Deserialize;
 
+// for an account under a ban that hasn't run out yet
+fn ban_response(account: &AccountDoc) -> Option<SdkResponse<Empty>> {
+    let now = get_duration_since_unix().as_secs() as i64;
+    if !account.is_ban_active(now) {
+        return None;
+    }
+
+    let duration = match account.ban_remaining(now) {
+        Some(v) => format!("for another {}", format_duration(v)),
+        None => String::from("permanently"),
+    };
+    let message = format!(
+        "you're banned {}. reason: {}",
+        duration,
+        account.ban_reason.as_deref().unwrap_or_default()
+    );
+    Some(SdkResponse::error(Retcode::AccountBanned, message))
+}
+
+// checked before the database is, so a forged or expired token costs nothing.
+// the stored token still has to match after, that's what revoking changes
+fn check_token(uid: u32, token: &str) -> Result<(), SdkResponse<Empty>> {
+    match token::verify(token) {
+        Ok(claims) if claims.uid == uid => Ok(()),
+        Ok(_) | Err(TokenError::Malformed | TokenError::BadSignature) => Err(SdkResponse::error(
+            Retcode::InvalidAccount,
+            "token mismatch",
+        )),
+        Err(TokenError::Expired) => {
+            Err(SdkResponse::error(Retcode::InvalidAccount, "token expired"))
+        }
+    }
+}
+
+// the token to hand back, a new one once the stored one is halfway through its
+// lifetime, expired, or from before tokens were signed
+async fn current_token(
+    storage: &Storage,
+    account: AccountDoc,
+) -> Result<String, SdkResponse<Empty>> {
+    let fresh =
+        token::verify(&account.token).is_ok_and(|v| v.uid == account.uid && !v.should_refresh());
+    if fresh {
+        return Ok(account.token);
+    }
+
+    let new_token = token::generate(account.uid);
+    if let Err(e) = storage.accounts.update_token(account.uid, &new_token).await {
+        tracing::error!("Updating token: {}", e);
+        return Err(SdkResponse::internal_error());
+    }
+    Ok(new_token)
+}
+
+// the two largest units, rounded up so the last minute doesn't read as 0m
+fn format_duration(seconds: i64) -> String {
+    let minutes = (seconds + 59) / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+fn account_missing() -> SdkResponse<Empty> {
+    SdkResponse::error(Retcode::InvalidAccount, "account doesn't exist")
+}
+
+fn token_mismatch() -> SdkResponse<Empty> {
+    SdkResponse::error(Retcode::InvalidAccount, "token mismatch")
+}
+
 #[derive(Deserialize)]
 struct LoginPasswordRequest {
     // this is username
     account: String,
-    // these needs a patch to work with, so we wont work with it
-    // password: String,
-    // is_crypto: bool,
+    // rsa encrypted with the client's key when `is_crypto`, needs a client
+    // patched with our public key to be readable
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    is_crypto: bool,
 }
 
+// `login_key` is `None` when `dispatch.verify_password` is off
 #[post("/{product}/mdk/shield/api/login")]
 pub async fn post_login_by_password(
     request: web::Json<LoginPasswordRequest>,
-    mongo_client: web::Data<MongoClient>,
-) -> impl Responder {
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-    let account = match AccountDoc::fetch_by_username(&ac_coll, &request.account).await {
+    storage: web::Data<Storage>,
+    login_key: web::Data<Option<LoginKey>>,
+) -> HttpResponse {
+    let account = match storage.accounts.fetch_by_username(&request.account).await {
         Ok(Some(v)) => v,
-        Ok(None) => {
-            return r#"{"data":{},"message":"account doesn't exist","retcode":1005}"#.to_string();
-        }
+        Ok(None) => return account_missing().into(),
         Err(e) => {
             tracing::error!("Fetching account by uid: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
+            return SdkResponse::internal_error().into();
         }
     };
 
-    let new_token: String;
-    match should_refresh_token(&account.token) {
-        Ok(true) => {
-            new_token = generate_token();
-            if let Err(e) = AccountDoc::update_token_by_uid(&ac_coll, account.uid, &new_token).await
-            {
-                tracing::error!("Updating token: {}", e);
-                return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
+    if let Some(login_key) = login_key.as_ref() {
+        let password = match request.is_crypto {
+            true => login_key.decrypt(&request.password),
+            false => Some(request.password.clone()),
+        };
+        let Some(password) = password else {
+            return SdkResponse::error(Retcode::BadRequest, "couldn't decrypt the password").into();
+        };
+
+        match verify_password(&password, &account.password_hash) {
+            Ok(true) => {}
+            Ok(false) => {
+                return SdkResponse::error(Retcode::WrongPassword, "wrong password").into();
+            }
+            Err(e) => {
+                tracing::error!("Verifying password: {}", e);
+                return SdkResponse::internal_error().into();
             }
         }
-        Err(e) => {
-            tracing::error!("Refreshing token: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
-        }
-        _ => new_token = account.token,
     }
 
-    format!(
-        r#"{{"data":{{"account":{{"area_code":"**","country":"ID","email":"{}@railgun.ps","is_email_verify":"1","token":"{}","uid":"{}"}},"device_grant_required":false,"reactivate_required":false,"realperson_required":false,"safe_mobile_required":false}},"message":"OK","retcode":0}}"#,
-        account.username, new_token, account.uid
-    )
+    if let Some(response) = ban_response(&account) {
+        return response.into();
+    }
+
+    let (uid, username) = (account.uid, account.username.clone());
+    match current_token(&storage, account).await {
+        Ok(token) => SdkResponse::ok(ShieldLoginData::new(uid, &username, token)).into(),
+        Err(response) => response.into(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -63,49 +150,35 @@ pub struct ShieldVerifyRequest {
 #[post("/{product}/mdk/shield/api/verify")]
 pub async fn post_login_by_token(
     request: web::Json<ShieldVerifyRequest>,
-    mongo_client: web::Data<MongoClient>,
-) -> impl Responder {
+    storage: web::Data<Storage>,
+) -> HttpResponse {
     let Ok(uid) = request.uid.parse::<u32>() else {
-        return r#"{"data":{},"message":"bad request","retcode":5}"#.to_string();
+        return SdkResponse::bad_request().into();
     };
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-    let account = match AccountDoc::fetch_by_uid(&ac_coll, uid).await {
+    if let Err(response) = check_token(uid, &request.token) {
+        return response.into();
+    }
+    let account = match storage.accounts.fetch_by_uid(uid).await {
         Ok(Some(v)) => v,
-        Ok(None) => {
-            return r#"{"data":{},"message":"account doesn't exist","retcode":1005}"#.to_string();
-        }
+        Ok(None) => return account_missing().into(),
         Err(e) => {
             tracing::error!("Fetching account by uid: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
+            return SdkResponse::internal_error().into();
         }
     };
 
-    if &account.token != &request.token {
-        return r#"{"data":{},"message":"token mismatch","retcode":1005}"#.to_string();
+    if account.token != request.token {
+        return token_mismatch().into();
     }
-
-    let new_token: String;
-    match should_refresh_token(&request.token) {
-        Ok(true) => {
-            new_token = generate_token();
-            if let Err(e) = AccountDoc::update_token_by_uid(&ac_coll, account.uid, &new_token).await
-            {
-                tracing::error!("Updating token: {}", e);
-                return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
-            }
-        }
-        Err(e) => {
-            tracing::error!("Refreshing token: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
-        }
-        _ => new_token = request.token.clone(),
+    if let Some(response) = ban_response(&account) {
+        return response.into();
     }
 
-    format!(
-        r#"{{"data":{{"account":{{"area_code":"**","country":"ID","email":"{}@railgun.ps","is_email_verify":"1","token":"{}","uid":"{}"}},"device_grant_required":false,"reactivate_required":false,"realperson_required":false,"safe_mobile_required":false}},"message":"OK","retcode":0}}"#,
-        account.username, new_token, account.uid
-    )
+    let (uid, username) = (account.uid, account.username.clone());
+    match current_token(&storage, account).await {
+        Ok(token) => SdkResponse::ok(ShieldLoginData::new(uid, &username, token)).into(),
+        Err(response) => response.into(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -131,52 +204,38 @@ impl GrantLoginRequest {
 #[post("/{product}/combo/granter/login/v2/login")]
 pub async fn post_grant_login(
     request: web::Json<GrantLoginRequest>,
-    mongo_client: web::Data<MongoClient>,
-) -> impl Responder {
+    storage: web::Data<Storage>,
+) -> HttpResponse {
     let Ok(data) = request.parse_data() else {
-        return r#"{"data":{},"message":"bad request","retcode":5}"#.to_string();
+        return SdkResponse::bad_request().into();
     };
     let Ok(uid) = data.uid.parse::<u32>() else {
-        return r#"{"data":{},"message":"bad request","retcode":5}"#.to_string();
+        return SdkResponse::bad_request().into();
     };
+    if let Err(response) = check_token(uid, &data.token) {
+        return response.into();
+    }
 
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-    let account = match AccountDoc::fetch_by_uid(&ac_coll, uid).await {
+    let account = match storage.accounts.fetch_by_uid(uid).await {
         Ok(Some(v)) => v,
-        Ok(None) => {
-            return r#"{"data":{},"message":"account doesn't exist","retcode":1005}"#.to_string();
-        }
+        Ok(None) => return account_missing().into(),
         Err(e) => {
             tracing::error!("Fetching account by uid: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
+            return SdkResponse::internal_error().into();
         }
     };
 
-    if &account.token != &data.token {
-        return r#"{"data":{},"message":"token mismatch","retcode":1005}"#.to_string();
+    if account.token != data.token {
+        return token_mismatch().into();
     }
-
-    let new_token: String;
-    match should_refresh_token(&data.token) {
-        Ok(true) => {
-            new_token = generate_token();
-            if let Err(e) = AccountDoc::update_token_by_uid(&ac_coll, uid, &new_token).await {
-                tracing::error!("Updating token: {}", e);
-                return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
-            }
-        }
-        Err(e) => {
-            tracing::error!("Refreshing token: {}", e);
-            return r#"{"data":{},"message":"internal error","retcode":2}"#.to_string();
-        }
-        _ => new_token = data.token,
+    if let Some(response) = ban_response(&account) {
+        return response.into();
     }
 
-    format!(
-        r#"{{"data":{{"account_type":1,"combo_id":"{}","combo_token":"{}","data":"{{\"guest\":false}}","heartbeat":false,"open_id":"{}"}},"message":"OK","retcode":0}}"#,
-        account.uid, new_token, account.uid
-    )
+    match current_token(&storage, account).await {
+        Ok(token) => SdkResponse::ok(GranterLoginData::new(uid, token)).into(),
+        Err(response) => response.into(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -188,24 +247,125 @@ struct RiskyCheckRequest {
 #[post("/account/risky/api/check")]
 pub async fn post_risky_check(
     request: web::Json<RiskyCheckRequest>,
-    mongo_client: web::Data<MongoClient>,
-) -> impl Responder {
+    storage: web::Data<Storage>,
+) -> HttpResponse {
     if &request.action_type != "login" {
-        return r#"{"data":{},"message":"OK","retcode":0}"#.to_string();
-    }
-
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-    match AccountDoc::fetch_by_username(&ac_coll, &request.username).await {
-        Ok(Some(v)) if v.is_banned => format!(
-            r#"{{"data":{{}},"message":"you're banned. reason: {}","retcode":1004}}"#,
-            v.ban_reason.unwrap_or_default()
-        ),
-        Ok(Some(_)) => r#"{"data":{},"message":"OK","retcode":0}"#.to_string(),
-        Ok(None) => r#"{"data":{},"message":"account doesn't exist","retcode":1005}"#.to_string(),
+        return SdkResponse::ok(Empty {}).into();
+    }
+
+    match storage.accounts.fetch_by_username(&request.username).await {
+        Ok(Some(v)) => match ban_response(&v) {
+            Some(response) => response.into(),
+            None => SdkResponse::ok(Empty {}).into(),
+        },
+        Ok(None) => account_missing().into(),
         Err(e) => {
             tracing::error!("Fetching account by username: {}", e);
-            r#"{"data":{},"message":"internal error","retcode":2}"#.to_string()
+            SdkResponse::internal_error().into()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::password::hash_password;
+    use actix_web::App;
+    use actix_web::test::{TestRequest, call_and_read_body_json, init_service};
+    use database::repo::memory::MemoryStorage;
+    use openssl::rsa::{Padding, Rsa};
+
+    async fn login(login_key: Option<LoginKey>, password: &str, is_crypto: bool) -> i64 {
+        token::set_secret(b"test secret".to_vec());
+        let storage = Storage::new(MemoryStorage::default());
+        let account = AccountDoc::new(1, "kiana", hash_password("hunter2").unwrap(), String::new());
+        storage.accounts.register(&account).await.unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(storage))
+                .app_data(web::Data::new(login_key))
+                .service(post_login_by_password),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/hkrpg_global/mdk/shield/api/login")
+            .set_json(serde_json::json!({
+                "account": "kiana",
+                "password": password,
+                "is_crypto": is_crypto,
+            }))
+            .to_request();
+        let body: serde_json::Value = call_and_read_body_json(&app, req).await;
+        body["retcode"].as_i64().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn login_checks_the_encrypted_password() {
+        let rsa = Rsa::generate(1024).unwrap();
+        let mut ciphertext = vec![0; rsa.size() as usize];
+        let len = rsa
+            .public_encrypt(b"hunter2", &mut ciphertext, Padding::PKCS1)
+            .unwrap();
+        let encrypted = rbase64::encode(&ciphertext[..len]);
+        let pem = rsa.private_key_to_pem().unwrap();
+        let key = || Some(LoginKey::new(Rsa::private_key_from_pem(&pem).unwrap()));
+
+        assert_eq!(login(key(), &encrypted, true).await, 0);
+        assert_eq!(login(key(), "hunter2", false).await, 0);
+        assert_eq!(login(key(), "hunter3", false).await, -101);
+        // not base64, and a ciphertext shorter than the key
+        let bad_request = Retcode::BadRequest.code() as i64;
+        assert_eq!(login(key(), "hunter2!", true).await, bad_request);
+        let short = rbase64::encode(&ciphertext[..len / 2]);
+        assert_eq!(login(key(), &short, true).await, bad_request);
+    }
+
+    #[actix_web::test]
+    async fn username_only_mode_ignores_the_password() {
+        assert_eq!(login(None, "", false).await, 0);
+    }
+
+    #[actix_web::test]
+    async fn token_login_needs_the_stored_signed_token() {
+        token::set_secret(b"test secret".to_vec());
+        let storage = Storage::new(MemoryStorage::default());
+        let mut account = AccountDoc::new(1, "kiana", String::new(), token::generate(1));
+        storage.accounts.register(&account).await.unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(storage.clone()))
+                .service(post_login_by_token),
+        )
+        .await;
+        let verify = |token: String| {
+            TestRequest::post()
+                .uri("/hkrpg_global/mdk/shield/api/verify")
+                .set_json(serde_json::json!({ "uid": "1", "token": token }))
+                .to_request()
+        };
+
+        let body: serde_json::Value =
+            call_and_read_body_json(&app, verify(account.token.clone())).await;
+        assert_eq!(body["retcode"], 0);
+        assert_eq!(body["data"]["account"]["token"], account.token);
+
+        // signed for someone else
+        let body: serde_json::Value =
+            call_and_read_body_json(&app, verify(token::generate(2))).await;
+        assert_eq!(body["retcode"], 1005);
+
+        // signed, but revoked since
+        account.token = token::generate(1);
+        let body: serde_json::Value = call_and_read_body_json(&app, verify(account.token)).await;
+        assert_eq!(body["retcode"], 1005);
+    }
+
+    #[test]
+    fn durations_round_up_to_the_minute() {
+        assert_eq!(format_duration(1), "1m");
+        assert_eq!(format_duration(60), "1m");
+        assert_eq!(format_duration(3 * 3600 + 61), "3h 2m");
+        assert_eq!(format_duration(2 * 86400 + 5 * 3600), "2d 5h");
+    }
+}
@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod dispatch;
 pub mod login;
 pub mod registration;
+pub mod sdk;
 pub mod srtools;
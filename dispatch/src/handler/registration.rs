@@ -1,9 +1,8 @@
 use crate::util::password::hash_password;
-use crate::util::token::generate_token;
 use actix_web::{HttpResponse, Responder, get, http::header::ContentType, post, web};
-use database::account::{AccountDoc, AccountMetaDoc};
-use database::srtools::{SRToolsDoc, SRToolsMetaDoc};
-use database::{DB_NAME, MongoClient};
+use common::token;
+use database::account::AccountDoc;
+use database::{Error, Storage};
 use serde::Deserialize;
 
 const REGISTER_PAGE: &str = include_str!("../../include/register.html");
@@ -24,7 +23,7 @@ struct RegisterRequest {
 #[post("/account/register")]
 pub async fn post_register(
     request: web::Json<RegisterRequest>,
-    mongo_client: web::Data<MongoClient>,
+    storage: web::Data<Storage>,
 ) -> impl Responder {
     if request.username.len() < 4 || request.username.len() > 12 {
         return HttpResponse::BadRequest().body("Username length must be between 4-12");
@@ -33,12 +32,11 @@ pub async fn post_register(
         return HttpResponse::BadRequest().body("Password length must be over 4");
     }
 
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-
-    match AccountDoc::check_username_taken(&ac_coll, &request.username).await {
+    // saves hashing a password for the common case, the unique index is what
+    // actually stops two registrations racing for the same name
+    match storage.accounts.username_taken(&request.username).await {
         Ok(true) => {
-            return HttpResponse::InternalServerError().body("Username is already taken");
+            return HttpResponse::Conflict().body("Username is already taken");
         }
         Err(e) => {
             tracing::error!("Checking username taken: {}", e);
@@ -55,8 +53,7 @@ pub async fn post_register(
         }
     };
 
-    let token = generate_token();
-    let uid = match AccountMetaDoc::get_next_uid(&AccountMetaDoc::get_collection(&db)).await {
+    let uid = match storage.accounts.next_uid().await {
         Ok(v) => v,
         Err(e) => {
             tracing::error!("Getting next uid: {}", e);
@@ -64,48 +61,56 @@ pub async fn post_register(
         }
     };
 
-    let new_account = AccountDoc {
-        uid,
-        username: request.username.clone(),
-        password_hash,
-        token,
-        is_banned: false,
-        ban_reason: None,
-    };
-
-    if let Err(e) = new_account.insert_to_collection(&ac_coll).await {
-        tracing::error!("Inserting new account: {}", e);
-        return HttpResponse::InternalServerError().body("Internal server error.");
-    }
+    let new_account = AccountDoc::new(uid, &request.username, password_hash, token::generate(uid));
 
-    match (SRToolsDoc {
-        uid,
-        username: request.username.clone(),
-        data: None,
-    })
-    .insert_to_collection(&SRToolsDoc::get_collection(&db))
-    .await
-    {
-        Err(e) => {
-            tracing::error!("Inserting srtoolsdoc: {}", e);
-            return HttpResponse::InternalServerError().body("Internal server error.");
+    match storage.accounts.register(&new_account).await {
+        Ok(()) => {}
+        Err(Error::DuplicateKey(_)) => {
+            return HttpResponse::Conflict().body("Username is already taken");
         }
-        _ => {}
-    };
-    match (SRToolsMetaDoc {
-        username: request.username.clone(),
-        next_sync_allowed: 0,
-        next_export_allowed: 0,
-    })
-    .insert_to_collection(&SRToolsMetaDoc::get_collection(&db))
-    .await
-    {
         Err(e) => {
-            tracing::error!("Inserting srtoolsmetadoc: {}", e);
+            tracing::error!("Registering account: {}", e);
             return HttpResponse::InternalServerError().body("Internal server error.");
         }
-        _ => {}
     }
 
     HttpResponse::Ok().body(format!("Register success! Your uid is {}", uid))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test};
+    use database::repo::memory::MemoryStorage;
+
+    #[actix_web::test]
+    async fn register_rejects_a_taken_username() {
+        token::set_secret(b"test secret".to_vec());
+        let storage = Storage::new(MemoryStorage::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(storage.clone()))
+                .service(post_register),
+        )
+        .await;
+        let body = serde_json::json!({ "username": "kiana", "password": "hunter2" });
+
+        let req = test::TestRequest::post()
+            .uri("/account/register")
+            .set_json(&body)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri("/account/register")
+            .set_json(&body)
+            .to_request();
+        let status = test::call_service(&app, req).await.status();
+        assert_eq!(status, actix_web::http::StatusCode::CONFLICT);
+
+        let account = storage.accounts.fetch_by_username("kiana").await.unwrap();
+        let srtools = storage.srtools.fetch_by_uid(account.unwrap().uid).await;
+        assert!(srtools.unwrap().is_some());
+        assert!(storage.srtools.fetch_meta("kiana").await.unwrap().is_some());
+    }
+}
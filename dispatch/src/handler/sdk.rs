@@ -0,0 +1,232 @@
+use crate::util::sdk_response::SdkResponse;
+use actix_web::{HttpResponse, get, post, route, web};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// what the client asks for while it boots, before anyone logs in. none of it
+// changes anything we do, so these are the answers an offline server gives
+
+#[derive(Serialize)]
+struct GranterConfig {
+    protocol: bool,
+    qr_enabled: bool,
+    log_level: &'static str,
+    announce_url: &'static str,
+    push_alias_type: u32,
+    disable_ysdk_guard: bool,
+    enable_announce_pic_popup: bool,
+    app_name: &'static str,
+    enable_user_center: bool,
+    functional_switch_configs: HashMap<String, bool>,
+}
+
+#[get("/{product}/combo/granter/api/getConfig")]
+pub async fn get_granter_config() -> HttpResponse {
+    SdkResponse::ok(GranterConfig {
+        protocol: true,
+        qr_enabled: false,
+        log_level: "INFO",
+        announce_url: "",
+        push_alias_type: 0,
+        disable_ysdk_guard: true,
+        enable_announce_pic_popup: false,
+        app_name: "railgun",
+        enable_user_center: true,
+        functional_switch_configs: HashMap::new(),
+    })
+    .into()
+}
+
+#[derive(Serialize)]
+struct ShieldConfig {
+    game_key: String,
+    client: &'static str,
+    identity: &'static str,
+    guest: bool,
+    ignore_versions: &'static str,
+    scene: &'static str,
+    name: &'static str,
+    disable_regist: bool,
+    enable_email_captcha: bool,
+    // no third party logins, there's nobody to ask about them
+    thirdparty: Vec<String>,
+    disable_mmt: bool,
+    server_guest: bool,
+    thirdparty_ignore: HashMap<String, String>,
+    enable_ps_bind_account: bool,
+    thirdparty_login_configs: HashMap<String, String>,
+    initialize_firebase: bool,
+    bbs_auth_login: bool,
+    bbs_auth_login_ignore: Vec<String>,
+    fetch_instance_id: bool,
+    enable_flash_login: bool,
+}
+
+#[get("/{product}/mdk/shield/api/loadConfig")]
+pub async fn get_shield_config(product: web::Path<String>) -> HttpResponse {
+    SdkResponse::ok(ShieldConfig {
+        game_key: product.into_inner(),
+        client: "PC",
+        identity: "I_IDENTITY",
+        guest: false,
+        ignore_versions: "",
+        scene: "S_NORMAL",
+        name: "railgun",
+        // registering goes through /account/register, not the sdk
+        disable_regist: true,
+        enable_email_captcha: false,
+        thirdparty: Vec::new(),
+        disable_mmt: true,
+        server_guest: false,
+        thirdparty_ignore: HashMap::new(),
+        enable_ps_bind_account: false,
+        thirdparty_login_configs: HashMap::new(),
+        initialize_firebase: false,
+        bbs_auth_login: false,
+        bbs_auth_login_ignore: Vec::new(),
+        fetch_instance_id: false,
+        enable_flash_login: false,
+    })
+    .into()
+}
+
+#[derive(Serialize)]
+struct ComboConfig {
+    vals: HashMap<&'static str, &'static str>,
+}
+
+// the client has asked for this with both methods across versions
+#[route(
+    "/{product}/combo/box/api/config/sdk/combo",
+    method = "GET",
+    method = "POST"
+)]
+pub async fn get_combo_config() -> HttpResponse {
+    SdkResponse::ok(ComboConfig {
+        vals: HashMap::from([
+            ("disable_email_bind_skip", "false"),
+            ("email_bind_remind", "false"),
+            ("email_bind_remind_interval", "7"),
+        ]),
+    })
+    .into()
+}
+
+#[derive(Serialize)]
+struct ProtocolVersion {
+    modified: bool,
+    protocol: Option<()>,
+}
+
+#[post("/{product}/combo/granter/api/compareProtocolVersion")]
+pub async fn post_compare_protocol_version() -> HttpResponse {
+    SdkResponse::ok(ProtocolVersion {
+        modified: false,
+        protocol: None,
+    })
+    .into()
+}
+
+#[derive(Serialize)]
+struct AgreementInfos {
+    marketing_agreements: Vec<String>,
+}
+
+#[get("/{product}/mdk/agreement/api/getAgreementInfos")]
+pub async fn get_agreement_infos() -> HttpResponse {
+    SdkResponse::ok(AgreementInfos {
+        marketing_agreements: Vec::new(),
+    })
+    .into()
+}
+
+// the abtest api has its own envelope, with `success` and no `{}` for data
+#[derive(Serialize)]
+struct ExperimentList {
+    retcode: i32,
+    success: bool,
+    message: &'static str,
+    data: Vec<String>,
+}
+
+#[post("/data_abtest_api/config/experiment/list")]
+pub async fn post_experiment_list() -> HttpResponse {
+    HttpResponse::Ok().json(ExperimentList {
+        retcode: 0,
+        success: true,
+        message: "",
+        data: Vec::new(),
+    })
+}
+
+// the client uploads logs and crash reports on its own, they're dropped here
+// so it doesn't keep retrying against a 404
+#[post("/sdk/dataUpload")]
+pub async fn post_sdk_data_upload() -> HttpResponse {
+    log_sink_response()
+}
+
+#[post("/crash/dataUpload")]
+pub async fn post_crash_data_upload() -> HttpResponse {
+    log_sink_response()
+}
+
+#[post("/apm/dataUpload")]
+pub async fn post_apm_data_upload() -> HttpResponse {
+    log_sink_response()
+}
+
+#[post("/common/h5log/log/batch")]
+pub async fn post_h5log_batch() -> HttpResponse {
+    log_sink_response()
+}
+
+fn log_sink_response() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "code": 0 }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use actix_web::test::{TestRequest, call_and_read_body_json, init_service};
+
+    #[actix_web::test]
+    async fn boot_endpoints_answer_offline() {
+        let app = init_service(
+            App::new()
+                .service(get_granter_config)
+                .service(get_shield_config)
+                .service(get_combo_config)
+                .service(post_compare_protocol_version)
+                .service(get_agreement_infos)
+                .service(post_experiment_list)
+                .service(post_sdk_data_upload),
+        )
+        .await;
+
+        let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+        let post = |uri: &str| TestRequest::post().uri(uri).to_request();
+
+        let body: serde_json::Value =
+            call_and_read_body_json(&app, get("/hkrpg_global/mdk/shield/api/loadConfig")).await;
+        assert_eq!(body["retcode"], 0);
+        assert_eq!(body["data"]["game_key"], "hkrpg_global");
+
+        for request in [
+            get("/hkrpg_global/combo/granter/api/getConfig"),
+            get("/hkrpg_global/combo/box/api/config/sdk/combo"),
+            post("/hkrpg_global/combo/box/api/config/sdk/combo"),
+            post("/hkrpg_global/combo/granter/api/compareProtocolVersion"),
+            get("/hkrpg_global/mdk/agreement/api/getAgreementInfos"),
+            post("/data_abtest_api/config/experiment/list"),
+        ] {
+            let uri = request.uri().clone();
+            let body: serde_json::Value = call_and_read_body_json(&app, request).await;
+            assert_eq!(body["retcode"], 0, "{}", uri);
+        }
+
+        let body: serde_json::Value = call_and_read_body_json(&app, post("/sdk/dataUpload")).await;
+        assert_eq!(body["code"], 0);
+    }
+}
@@ -1,20 +1,13 @@
 use crate::util::password::verify_password;
 use actix_web::{HttpResponse, Responder, get, post, web};
 use common::time::get_duration_since_unix;
-use database::account::AccountDoc;
-use database::srtools::{SRToolsData, SRToolsDoc, SRToolsMetaDoc};
-use database::{DB_NAME, MongoClient};
+use database::Storage;
+use database::srtools::SRToolsData;
 use serde::Deserialize;
 
 #[get("/srtools/user/{username}")]
-pub async fn get_json(
-    username: web::Path<String>,
-    mongo_client: web::Data<MongoClient>,
-) -> impl Responder {
-    let db = mongo_client.database(DB_NAME);
-    let st_coll = SRToolsDoc::get_collection(&db);
-
-    match SRToolsDoc::fetch_by_username(&st_coll, &username).await {
+pub async fn get_json(username: web::Path<String>, storage: web::Data<Storage>) -> impl Responder {
+    match storage.srtools.fetch_by_username(&username).await {
         Ok(Some(v)) => HttpResponse::Ok().json(v),
         Ok(None) => HttpResponse::Ok().json("\"not found\""),
         Err(e) => {
@@ -34,11 +27,9 @@ struct SRToolSyncRequest {
 #[post("/srtools/sync")]
 pub async fn post_sync(
     request: web::Json<SRToolSyncRequest>,
-    mongo_client: web::Data<MongoClient>,
+    storage: web::Data<Storage>,
 ) -> impl Responder {
-    let db = mongo_client.database(DB_NAME);
-    let ac_coll = AccountDoc::get_collection(&db);
-    let account = match AccountDoc::fetch_by_username(&ac_coll, &request.username).await {
+    let account = match storage.accounts.fetch_by_username(&request.username).await {
         Ok(Some(v)) => v,
         Ok(None) => {
             return HttpResponse::NotFound()
@@ -64,8 +55,7 @@ pub async fn post_sync(
         _ => {}
     };
 
-    let stm_coll = SRToolsMetaDoc::get_collection(&db);
-    let meta = match SRToolsMetaDoc::fetch_by_username(&stm_coll, &request.username).await {
+    let meta = match storage.srtools.fetch_meta(&request.username).await {
         Ok(Some(v)) => v,
         Ok(None) => {
             return HttpResponse::NotFound()
@@ -87,14 +77,20 @@ pub async fn post_sync(
     }
 
     if let Some(v) = &request.data {
-        let st_coll = SRToolsDoc::get_collection(&db);
-        if let Err(e) = SRToolsDoc::set_srtools_by_username(&st_coll, &request.username, v).await {
-            tracing::error!("Setting srtoolsdata by username: {}", e);
+        if let Err(e) = v.validate() {
+            return HttpResponse::BadRequest()
+                .body(format!(r#"{{"status":400,"message":"{}"}}"#, e));
         }
 
-        if let Err(e) =
-            SRToolsMetaDoc::update_next_sync_for_username(&stm_coll, &request.username).await
+        if let Err(e) = storage
+            .srtools
+            .set_data_by_username(&request.username, v)
+            .await
         {
+            tracing::error!("Setting srtoolsdata by username: {}", e);
+        }
+
+        if let Err(e) = storage.srtools.update_next_sync(&request.username).await {
             tracing::error!("Updating next sync: {}", e);
         }
     }
@@ -1,14 +1,17 @@
 use actix_cors::Cors;
 use actix_web::{App, HttpServer, middleware, web};
-use common::server_config::DISPATCH_BIND_TARGET;
-use database::MongoClient;
+use database::Storage;
 use reqwest::Client;
+use std::path::Path;
 
 mod handler;
 mod util;
 
 use handler::*;
 use util::certs;
+use util::client_secret_key::ClientSecretKey;
+use util::login_key::{self, LoginKey};
+use util::rate_limit::RateLimiter;
 
 // just adding this to test how long will it compile
 use common::resource::ExcelOutput;
@@ -22,10 +25,79 @@ async fn main() -> std::io::Result<()> {
         println!("{} {}", ex.is_some(), lv.is_some());
     }
     common::init_tracing();
+    let config = match common::config::init() {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Loading config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if config.database.migrations_dry_run {
+        if let Err(e) = database::dry_run_migrations().await {
+            tracing::error!("Dry running migrations: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = common::token::init(Path::new(&config.certs.token_secret_file)) {
+        tracing::error!("Loading token secret: {}", e);
+        std::process::exit(1);
+    }
+
     certs::check_cert_exists();
 
     let ssl_acceptor = certs::tls_builder();
-    let mongo_client: MongoClient = database::new_mongo_client().await;
+
+    // `None` is the username only login
+    let login_key: Option<LoginKey> = match config.dispatch.verify_password {
+        true => {
+            login_key::check_login_key_exists();
+            match LoginKey::load() {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    tracing::error!("Loading login key: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        false => {
+            tracing::warn!(
+                "dispatch.verify_password is off, logging in only takes a username. turn it on \
+                (RAILGUN_DISPATCH_VERIFY_PASSWORD=true) once the client is patched with {}",
+                config.certs.login_public_key_file
+            );
+            None
+        }
+    };
+    let login_key = web::Data::new(login_key);
+    // `None` leaves `client_secret_key` out, for clients patched to skip the xor
+    let client_secret_key: Option<ClientSecretKey> = match config.gameserver.xor_enabled {
+        true => match ClientSecretKey::load() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::error!("Loading {}: {}", config.certs.dispatch_seed_file, e);
+                std::process::exit(1);
+            }
+        },
+        false => None,
+    };
+    let client_secret_key = web::Data::new(client_secret_key);
+    // made out here so every worker counts against the same buckets
+    let rate_limiter = web::Data::new(RateLimiter::new(&config.dispatch.rate_limits));
+    let storage: Storage = match database::new_storage().await {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Opening database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let admin_api_enabled = !config.dispatch.admin_api_key.is_empty();
+    if admin_api_enabled {
+        tracing::info!("admin api enabled at /admin");
+    }
 
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -35,22 +107,41 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .wrap(cors)
+            .wrap(middleware::from_fn(util::rate_limit::rate_limit_middleware))
             .wrap(middleware::from_fn(util::logging::logger_middleware))
-            .app_data(web::Data::new(mongo_client.clone()))
+            .app_data(rate_limiter.clone())
+            .app_data(web::Data::new(storage.clone()))
             .app_data(web::Data::new(Client::new()))
+            .app_data(login_key.clone())
+            .app_data(client_secret_key.clone())
             .service(dispatch::get_query_gateway)
             .service(dispatch::get_query_dispatch)
             .service(login::post_login_by_password)
             .service(login::post_login_by_token)
             .service(login::post_grant_login)
             .service(login::post_risky_check)
+            .service(sdk::get_granter_config)
+            .service(sdk::get_shield_config)
+            .service(sdk::get_combo_config)
+            .service(sdk::post_compare_protocol_version)
+            .service(sdk::get_agreement_infos)
+            .service(sdk::post_experiment_list)
+            .service(sdk::post_sdk_data_upload)
+            .service(sdk::post_crash_data_upload)
+            .service(sdk::post_apm_data_upload)
+            .service(sdk::post_h5log_batch)
             .service(registration::get_register)
             .service(registration::post_register)
             .service(srtools::get_json)
             // .service(srtools::options_cors)
             .service(srtools::post_sync)
+            .configure(|cfg| {
+                if admin_api_enabled {
+                    admin::configure(cfg);
+                }
+            })
     })
-    .bind_openssl(DISPATCH_BIND_TARGET, ssl_acceptor)?
+    .bind_openssl(config.dispatch_bind_target(), ssl_acceptor)?
     .run()
     .await
 }
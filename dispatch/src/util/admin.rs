@@ -0,0 +1,76 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use common::config;
+
+pub async fn admin_auth_middleware<B: MessageBody>(
+    request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if !is_authorized(&request, &config::get().dispatch.admin_api_key) {
+        tracing::warn!(
+            "rejected admin request from {:?}: {} {}",
+            request.peer_addr(),
+            request.method(),
+            request.uri()
+        );
+        let response = HttpResponse::Unauthorized().body("Invalid api key");
+        return Ok(request.into_response(response).map_into_right_body());
+    }
+
+    next.call(request)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+fn is_authorized(request: &ServiceRequest, api_key: &str) -> bool {
+    let Some(given) = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+
+    // an empty key is the admin api turned off, nothing may match it
+    !api_key.is_empty() && constant_time_eq(given.as_bytes(), api_key.as_bytes())
+}
+
+// looks at every byte, so how long a wrong key takes doesn't say how much of it was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const KEY: &str = "0123456789abcdef";
+
+    fn request(authorization: &str) -> ServiceRequest {
+        TestRequest::default()
+            .insert_header((AUTHORIZATION, authorization))
+            .to_srv_request()
+    }
+
+    #[test]
+    fn only_the_bearer_key_is_accepted() {
+        assert!(is_authorized(&request("Bearer 0123456789abcdef"), KEY));
+        assert!(!is_authorized(&request("Bearer 0123456789abcdeF"), KEY));
+        assert!(!is_authorized(&request("Bearer 0123456789abcde"), KEY));
+        assert!(!is_authorized(&request("0123456789abcdef"), KEY));
+        assert!(!is_authorized(
+            &TestRequest::default().to_srv_request(),
+            KEY
+        ));
+    }
+
+    #[test]
+    fn an_empty_key_accepts_nothing() {
+        assert!(!is_authorized(&request("Bearer "), ""));
+    }
+}
@@ -1,38 +1,79 @@
+use common::config::HotfixUpstreamConfig;
 use common::proto::GateServer;
 use common::proto::prost::Message;
+use common::time::get_duration_since_unix;
 use database::hotfix::HotfixDoc;
 use reqwest::Client;
+use std::time::Duration;
 
-const PROXY_HOST: &str = "proxy1.neonteam.dev";
-const CN_PROD_HOST: &str = "prod-gf-cn-dp01.bhsr.com";
-const CN_BETA_HOST: &str = "beta-release01-cn.bhsr.com";
-const OS_PROD_HOST: &str = "prod-official-asia-dp01.starrails.com";
-const OS_BETA_HOST: &str = "beta-release01-asia.starrails.com";
+const GATEWAY_QUERY: &str =
+    "language_type=1&platform_type=2&channel_id=1&sub_channel_id=1&is_need_url=1&account_type=1";
 
 pub async fn fetch_hotfix_from_official(
     reqwest_client: &Client,
+    upstreams: &[HotfixUpstreamConfig],
     version: &str,
     dispatch_seed: &str,
 ) -> Result<HotfixDoc, &'static str> {
-    let host = match version {
-        v if v.starts_with("CNPROD") => CN_PROD_HOST,
-        v if v.starts_with("CNBETA") => CN_BETA_HOST,
-        v if v.starts_with("OSPROD") => OS_PROD_HOST,
-        v if v.starts_with("OSBETA") => OS_BETA_HOST,
-        _ => return Err("Invalid version."),
+    let Some(upstream) = upstreams
+        .iter()
+        .find(|v| version.starts_with(&v.version_prefix))
+    else {
+        return Err("Invalid version.");
     };
 
-    let target = format!(
-        "https://{}/{}/query_gateway?version={}&dispatch_seed={}&language_type=1&platform_type=2&channel_id=1&sub_channel_id=1&is_need_url=1&account_type=1",
-        PROXY_HOST, host, version, dispatch_seed
-    );
+    let target = gateway_url(upstream, version, dispatch_seed);
+    let body = fetch_with_retries(reqwest_client, upstream, &target).await?;
+    decode_hotfix(version, &body)
+}
 
-    let reqwest_rsp = match reqwest_client.get(&target).send().await {
-        Ok(v) => v.text().await.unwrap(),
-        Err(_) => return Err("Reqwest error. Game version is probably not supported."),
+fn gateway_url(upstream: &HotfixUpstreamConfig, version: &str, dispatch_seed: &str) -> String {
+    let url = upstream.url.trim_end_matches('/');
+    let base = match upstream.proxy_url.trim_end_matches('/') {
+        "" => url.to_string(),
+        proxy => {
+            let host = url.split_once("://").map_or(url, |(_, host)| host);
+            format!("{}/{}", proxy, host)
+        }
     };
+    format!(
+        "{}/query_gateway?version={}&dispatch_seed={}&{}",
+        base, version, dispatch_seed, GATEWAY_QUERY
+    )
+}
+
+async fn fetch_with_retries(
+    reqwest_client: &Client,
+    upstream: &HotfixUpstreamConfig,
+    target: &str,
+) -> Result<String, &'static str> {
+    let timeout = Duration::from_secs(upstream.timeout_seconds);
+    let attempts = upstream.retries + 1;
+
+    for attempt in 1..=attempts {
+        let result = async {
+            reqwest_client
+                .get(target)
+                .timeout(timeout)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(v) => return Ok(v),
+            Err(e) => tracing::warn!("AutoHotfix attempt {}/{}: {}", attempt, attempts, e),
+        }
+    }
+
+    Err("Reqwest error. Game version is probably not supported.")
+}
 
-    let Ok(base64_decoded) = rbase64::decode(&reqwest_rsp) else {
+fn decode_hotfix(version: &str, body: &str) -> Result<HotfixDoc, &'static str> {
+    let Ok(base64_decoded) = rbase64::decode(body) else {
         return Err("Response wasn't valid base64");
     };
     let Ok(gateserver) = GateServer::decode(base64_decoded.as_ref()) else {
@@ -51,7 +92,115 @@ pub async fn fetch_hotfix_from_official(
         mdk_res_version: gateserver.mdk_res_version,
         asset_bundle_url: gateserver.asset_bundle_url,
         ex_resource_url: gateserver.ex_resource_url,
+        fetched_at: get_duration_since_unix().as_secs() as i64,
     };
 
     Ok(hotfix_doc)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, HttpServer, web};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const VERSION: &str = "OSPROD3.3.0";
+
+    fn upstream(url: &str, proxy_url: &str, retries: u32) -> HotfixUpstreamConfig {
+        HotfixUpstreamConfig {
+            version_prefix: String::from("OSPROD"),
+            url: url.to_string(),
+            proxy_url: proxy_url.to_string(),
+            timeout_seconds: 5,
+            retries,
+        }
+    }
+
+    fn gateserver(asset_bundle_url: &str) -> String {
+        let gateserver = GateServer {
+            asset_bundle_url: asset_bundle_url.to_string(),
+            lua_url: String::from("https://example.com/lua"),
+            ifix_version: String::from("7"),
+            ..Default::default()
+        };
+        rbase64::encode(&gateserver.encode_to_vec())
+    }
+
+    // a stand-in for the official dispatch, answers every path with `body`
+    // after failing the first `failures` requests with a 500
+    fn mock_upstream(body: String, failures: u32) -> String {
+        let served = Arc::new(AtomicU32::new(0));
+        let server = HttpServer::new(move || {
+            let (body, served) = (body.clone(), served.clone());
+            App::new().default_service(web::to(move || {
+                let (body, served) = (body.clone(), served.clone());
+                async move {
+                    match served.fetch_add(1, Ordering::SeqCst) < failures {
+                        true => HttpResponse::InternalServerError().finish(),
+                        false => HttpResponse::Ok().body(body),
+                    }
+                }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        url
+    }
+
+    #[test]
+    fn proxied_upstreams_put_the_host_in_the_path() {
+        let direct = upstream("https://dp.example.com/", "", 0);
+        let proxied = upstream("https://dp.example.com", "https://proxy.example.com/", 0);
+
+        assert!(gateway_url(&direct, VERSION, "seed").starts_with(
+            "https://dp.example.com/query_gateway?version=OSPROD3.3.0&dispatch_seed=seed&"
+        ));
+        assert!(gateway_url(&proxied, VERSION, "seed").starts_with(
+            "https://proxy.example.com/dp.example.com/query_gateway?version=OSPROD3.3.0&"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn fetches_and_decodes_from_the_upstream() {
+        let url = mock_upstream(gateserver("https://example.com/asb"), 1);
+        let upstreams = [upstream(&url, "", 1)];
+
+        let hotfix = fetch_hotfix_from_official(&Client::new(), &upstreams, VERSION, "seed")
+            .await
+            .unwrap();
+        assert_eq!(hotfix.version, VERSION);
+        assert_eq!(hotfix.asset_bundle_url, "https://example.com/asb");
+        assert_eq!(hotfix.mdk_res_url, "https://example.com/lua");
+        assert_eq!(hotfix.ifix_version, "7");
+        assert!(hotfix.fetched_at > 0);
+    }
+
+    #[actix_web::test]
+    async fn failures_past_the_retries_are_errors() {
+        let url = mock_upstream(gateserver("https://example.com/asb"), 2);
+        let upstreams = [upstream(&url, "", 1)];
+
+        let result = fetch_hotfix_from_official(&Client::new(), &upstreams, VERSION, "seed").await;
+        assert!(result.is_err());
+    }
+
+    #[actix_web::test]
+    async fn hotfixes_without_resource_urls_are_rejected() {
+        let url = mock_upstream(gateserver(""), 0);
+        let upstreams = [upstream(&url, "", 0)];
+
+        let result = fetch_hotfix_from_official(&Client::new(), &upstreams, VERSION, "seed").await;
+        assert_eq!(
+            result.unwrap_err(),
+            "AssetBundleUrl and ExResourceUrl is empty."
+        );
+
+        let result =
+            fetch_hotfix_from_official(&Client::new(), &upstreams, "CNPROD3.3.0", "").await;
+        assert_eq!(result.unwrap_err(), "Invalid version.");
+    }
+}
@@ -11,10 +11,11 @@ use openssl::rsa::Rsa;
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 use openssl::x509::{X509, X509Extension, X509NameBuilder};
 
-use common::server_config::{CERT_DIR, CRT_FILE_PATH, DNS, HOST, KEY_FILE_PATH};
+use common::config;
 
 fn generate_cert() {
-    let cert_dir = Path::new(CERT_DIR);
+    let config = config::get();
+    let cert_dir = Path::new(&config.certs.dir);
 
     if !cert_dir.exists() {
         fs::create_dir_all(cert_dir).unwrap();
@@ -36,7 +37,7 @@ fn generate_cert() {
 
     let mut name_builder = X509NameBuilder::new().unwrap();
     name_builder
-        .append_entry_by_nid(Nid::COMMONNAME, HOST)
+        .append_entry_by_nid(Nid::COMMONNAME, &config.host)
         .unwrap();
     let name = name_builder.build();
     x509_builder.set_subject_name(&name).unwrap();
@@ -49,7 +50,7 @@ fn generate_cert() {
         None,
         None,
         "subjectAltName",
-        &format!("IP:{},DNS:{}", HOST, DNS),
+        &format!("IP:{},DNS:{}", config.host, config.dns),
     )
     .unwrap();
     x509_builder.append_extension(subject_alt_name).unwrap();
@@ -60,27 +61,28 @@ fn generate_cert() {
     let pem_cert = cert.to_pem().unwrap();
     let pem_key = pkey.private_key_to_pem_pkcs8().unwrap();
 
-    File::create(KEY_FILE_PATH)
+    File::create(&config.certs.key_file)
         .unwrap()
         .write_all(&pem_key)
         .unwrap();
 
-    File::create(CRT_FILE_PATH)
+    File::create(&config.certs.crt_file)
         .unwrap()
         .write_all(&pem_cert)
         .unwrap();
 
-    tracing::info!("certs generated successfully in {}", CERT_DIR);
+    tracing::info!("certs generated successfully in {}", config.certs.dir);
 }
 
 pub fn tls_builder() -> SslAcceptorBuilder {
+    let config = config::get();
     let mut tls_builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
 
     tls_builder
-        .set_private_key_file(KEY_FILE_PATH, SslFiletype::PEM)
+        .set_private_key_file(&config.certs.key_file, SslFiletype::PEM)
         .unwrap();
     tls_builder
-        .set_certificate_chain_file(CRT_FILE_PATH)
+        .set_certificate_chain_file(&config.certs.crt_file)
         .unwrap();
 
     tls_builder.check_private_key().unwrap();
@@ -88,13 +90,14 @@ pub fn tls_builder() -> SslAcceptorBuilder {
 }
 
 pub fn check_cert_exists() {
+    let config = config::get();
     tracing::info!("checking certs.");
-    if !(Path::new(CRT_FILE_PATH).exists() && Path::new(KEY_FILE_PATH).exists()) {
+    if !(Path::new(&config.certs.crt_file).exists() && Path::new(&config.certs.key_file).exists()) {
         tracing::warn!("missing cert files, generating.");
         generate_cert();
         tracing::info!(
             "please install them as root certificates. dir: {}",
-            CERT_DIR
+            config.certs.dir
         );
         tracing::info!("once installed, press enter to continue.");
         stdin().read_line(&mut String::with_capacity(1)).unwrap();
@@ -0,0 +1,15 @@
+use std::fs;
+
+use common::config;
+
+// what `query_gateway` hands the client as `client_secret_key`, the base64 of
+// the ec2b blob it derives its pre-login xor key from
+pub struct ClientSecretKey(pub String);
+
+impl ClientSecretKey {
+    pub fn load() -> std::io::Result<Self> {
+        let config = config::get();
+        let ec2b = fs::read(&config.certs.dispatch_seed_file)?;
+        Ok(Self(rbase64::encode(&ec2b)))
+    }
+}
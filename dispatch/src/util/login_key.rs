@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use openssl::pkey::Private;
+use openssl::rsa::{Padding, Rsa};
+
+use common::config;
+
+// decrypts the password field of the sdk login
+pub struct LoginKey {
+    rsa: Rsa<Private>,
+}
+
+impl LoginKey {
+    pub fn new(rsa: Rsa<Private>) -> Self {
+        Self { rsa }
+    }
+
+    pub fn load() -> std::io::Result<Self> {
+        let config = config::get();
+        let pem = fs::read(&config.certs.login_private_key_file)?;
+        let rsa = Rsa::private_key_from_pem(&pem).map_err(std::io::Error::other)?;
+        Ok(Self::new(rsa))
+    }
+
+    // `encrypted` is base64 of the pkcs1 padded ciphertext, `None` when it
+    // wasn't made with our public key
+    pub fn decrypt(&self, encrypted: &str) -> Option<String> {
+        let ciphertext = rbase64::decode(encrypted).ok()?;
+        let mut plaintext = vec![0; self.rsa.size() as usize];
+        let len = self
+            .rsa
+            .private_decrypt(&ciphertext, &mut plaintext, Padding::PKCS1)
+            .ok()?;
+        plaintext.truncate(len);
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+fn generate_login_key() {
+    let config = config::get();
+    let cert_dir = Path::new(&config.certs.dir);
+
+    if !cert_dir.exists() {
+        fs::create_dir_all(cert_dir).unwrap();
+    }
+
+    let rsa = Rsa::generate(2048).unwrap();
+
+    File::create(&config.certs.login_private_key_file)
+        .unwrap()
+        .write_all(&rsa.private_key_to_pem().unwrap())
+        .unwrap();
+
+    File::create(&config.certs.login_public_key_file)
+        .unwrap()
+        .write_all(&rsa.public_key_to_pem().unwrap())
+        .unwrap();
+
+    tracing::info!(
+        "login key generated, patch the client's login rsa key with {}",
+        config.certs.login_public_key_file
+    );
+}
+
+pub fn check_login_key_exists() {
+    let config = config::get();
+    if !Path::new(&config.certs.login_private_key_file).exists() {
+        tracing::warn!("missing login key, generating.");
+        generate_login_key();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypts_what_the_public_key_encrypted() {
+        let rsa = Rsa::generate(1024).unwrap();
+        let mut ciphertext = vec![0; rsa.size() as usize];
+        let len = rsa
+            .public_encrypt(b"hunter2", &mut ciphertext, Padding::PKCS1)
+            .unwrap();
+        ciphertext.truncate(len);
+        let key = LoginKey::new(rsa);
+
+        assert_eq!(
+            key.decrypt(&rbase64::encode(&ciphertext)).as_deref(),
+            Some("hunter2")
+        );
+        assert_eq!(key.decrypt("hunter2!"), None);
+        assert_eq!(key.decrypt(&rbase64::encode(&ciphertext[..len / 2])), None);
+    }
+}
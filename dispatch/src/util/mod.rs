@@ -1,5 +1,9 @@
+pub mod admin;
 pub mod auto_hotfix;
 pub mod certs;
+pub mod client_secret_key;
 pub mod logging;
+pub mod login_key;
 pub mod password;
-pub mod token;
+pub mod rate_limit;
+pub mod sdk_response;
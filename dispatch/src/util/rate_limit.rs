@@ -0,0 +1,319 @@
+use crate::util::sdk_response::{Retcode, SdkResponse};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ResourceDef, ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::middleware::Next;
+use actix_web::web::{self, Bytes};
+use actix_web::{Error, HttpResponse};
+use common::config::{BucketConfig, RateLimitConfig};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// buckets that have refilled are dropped this often, a full bucket is the same
+// as no bucket
+const SWEEP_EVERY: Duration = Duration::from_secs(60);
+// past this many buckets the oldest one goes to make room, so a flood of
+// addresses can't grow the map between sweeps
+const MAX_BUCKETS: usize = 16384;
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+enum BucketKey {
+    Ip(usize, IpAddr),
+    Account(usize, String),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refilled = self.tokens + elapsed * limit.per_minute as f64 / 60.0;
+        self.tokens = refilled.min(limit.burst as f64);
+        self.updated = now;
+    }
+}
+
+struct Buckets {
+    map: HashMap<BucketKey, Bucket>,
+    // keys in the order they were made, the front is evicted first
+    order: VecDeque<BucketKey>,
+    swept: Instant,
+}
+
+pub struct RateLimiter {
+    routes: Vec<(ResourceDef, RateLimitConfig)>,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(routes: &[RateLimitConfig]) -> Self {
+        Self {
+            routes: routes
+                .iter()
+                .map(|v| (ResourceDef::new(v.route.as_str()), v.clone()))
+                .collect(),
+            buckets: Mutex::new(Buckets {
+                map: HashMap::new(),
+                order: VecDeque::new(),
+                swept: Instant::now(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Buckets> {
+        self.buckets.lock().unwrap()
+    }
+
+    fn route(&self, path: &str) -> Option<(usize, &RateLimitConfig)> {
+        self.routes
+            .iter()
+            .enumerate()
+            .find(|(_, (resource, _))| resource.is_match(path))
+            .map(|(i, (_, config))| (i, config))
+    }
+
+    // `Err` is how long until the bucket has a token again
+    fn take(&self, key: BucketKey, limit: &BucketConfig, now: Instant) -> Result<(), Duration> {
+        let mut guard = self.lock();
+        let buckets = &mut *guard;
+        if now.saturating_duration_since(buckets.swept) >= SWEEP_EVERY {
+            buckets.map.retain(|key, bucket| {
+                let limit = self.limit_for(key);
+                bucket.refill(limit, now);
+                bucket.tokens < limit.burst as f64
+            });
+            let map = &buckets.map;
+            buckets.order.retain(|key| map.contains_key(key));
+            buckets.swept = now;
+        }
+
+        if !buckets.map.contains_key(&key) {
+            while buckets.map.len() >= MAX_BUCKETS {
+                let Some(oldest) = buckets.order.pop_front() else {
+                    break;
+                };
+                buckets.map.remove(&oldest);
+            }
+            buckets.order.push_back(key.clone());
+        }
+
+        let bucket = buckets.map.entry(key).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+        });
+        bucket.refill(limit, now);
+
+        if bucket.tokens < 1.0 {
+            let wait = (1.0 - bucket.tokens) * 60.0 / limit.per_minute as f64;
+            return Err(Duration::from_secs_f64(wait));
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn limit_for(&self, key: &BucketKey) -> &BucketConfig {
+        let (index, per_ip) = match key {
+            BucketKey::Ip(i, _) => (*i, true),
+            BucketKey::Account(i, _) => (*i, false),
+        };
+        let config = &self.routes[index].1;
+        // a key is only ever made for a limit that's set
+        match per_ip {
+            true => config.per_ip.as_ref().unwrap(),
+            false => config.per_account.as_ref().unwrap(),
+        }
+    }
+}
+
+pub async fn rate_limit_middleware<B: MessageBody>(
+    mut request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(limiter) = request.app_data::<web::Data<RateLimiter>>().cloned() else {
+        return next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    let Some((index, route)) = limiter.route(request.path()) else {
+        return next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    let now = Instant::now();
+    let mut result = Ok(());
+    if let (Some(limit), Some(addr)) = (&route.per_ip, request.peer_addr()) {
+        result = limiter.take(BucketKey::Ip(index, addr.ip()), limit, now);
+    }
+    if let (Ok(()), Some(limit)) = (result, &route.per_account) {
+        // the handler still needs the body, so it's put back after peeking
+        let body = request.extract::<Bytes>().await?;
+        let account = account_from_body(&body, &route.account_field);
+        request.set_payload(body.into());
+        if let Some(account) = account {
+            result = limiter.take(BucketKey::Account(index, account), limit, now);
+        }
+    }
+
+    match result {
+        Ok(()) => next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+        Err(retry_after) => {
+            tracing::warn!(
+                "rate limited {:?}: {} {}",
+                request.peer_addr(),
+                request.method(),
+                request.uri()
+            );
+            let response = limited_response(route, retry_after);
+            Ok(request.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+// strings as they are, numbers (like a uid) as their text
+fn account_from_body(body: &[u8], field: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    match value.get(field)? {
+        serde_json::Value::String(v) => Some(v.clone()),
+        serde_json::Value::Number(v) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+fn limited_response(route: &RateLimitConfig, retry_after: Duration) -> HttpResponse {
+    let secs = retry_after.as_secs() + 1;
+    if route.sdk_response {
+        let message = format!("too many requests, try again in {}s", secs);
+        return SdkResponse::error(Retcode::Fail, message).into();
+    }
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, secs))
+        .body("Too many requests, try again later")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use actix_web::{App, HttpResponse, middleware, post};
+
+    fn limit(burst: u32, per_minute: u32) -> BucketConfig {
+        BucketConfig { burst, per_minute }
+    }
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let limiter = RateLimiter::new(&[]);
+        let limit = limit(2, 60);
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.take(BucketKey::Ip(0, ip), &limit, start).is_ok());
+        assert!(limiter.take(BucketKey::Ip(0, ip), &limit, start).is_ok());
+        let wait = limiter
+            .take(BucketKey::Ip(0, ip), &limit, start)
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.take(BucketKey::Ip(0, ip), &limit, later).is_ok());
+        assert!(limiter.take(BucketKey::Ip(0, ip), &limit, later).is_err());
+    }
+
+    #[test]
+    fn refilled_buckets_are_swept_on_an_interval() {
+        let limiter = RateLimiter::new(&[RateLimitConfig {
+            route: String::from("/login"),
+            per_ip: Some(limit(1, 60)),
+            per_account: None,
+            account_field: String::from("account"),
+            sdk_response: false,
+        }]);
+        let limit = limit(1, 60);
+        let start = limiter.lock().swept;
+        let ip = |i: u8| BucketKey::Ip(0, IpAddr::from([10, 0, 0, i]));
+
+        assert!(limiter.take(ip(1), &limit, start).is_ok());
+        // refilled, but the sweep isn't due yet
+        assert!(
+            limiter
+                .take(ip(2), &limit, start + Duration::from_secs(2))
+                .is_ok()
+        );
+        assert_eq!(limiter.lock().map.len(), 2);
+
+        let later = start + SWEEP_EVERY + Duration::from_millis(500);
+        assert!(limiter.take(ip(3), &limit, later).is_ok());
+        let buckets = limiter.lock();
+        assert_eq!(buckets.map.len(), 1);
+        assert_eq!(buckets.order.len(), 1);
+        assert!(buckets.map.contains_key(&ip(3)));
+    }
+
+    #[test]
+    fn oldest_bucket_is_evicted_at_the_cap() {
+        let limiter = RateLimiter::new(&[]);
+        let limit = limit(1, 1);
+        let now = limiter.lock().swept;
+        let account = |i: usize| BucketKey::Account(0, i.to_string());
+
+        for i in 0..=MAX_BUCKETS {
+            assert!(limiter.take(account(i), &limit, now).is_ok());
+        }
+        let buckets = limiter.lock();
+        assert_eq!(buckets.map.len(), MAX_BUCKETS);
+        assert!(!buckets.map.contains_key(&account(0)));
+        assert!(buckets.map.contains_key(&account(1)));
+        assert!(buckets.map.contains_key(&account(MAX_BUCKETS)));
+    }
+
+    #[post("/{product}/login")]
+    async fn login(body: web::Json<serde_json::Value>) -> HttpResponse {
+        HttpResponse::Ok().json(body.into_inner())
+    }
+
+    #[actix_web::test]
+    async fn accounts_are_limited_apart_and_bodies_survive() {
+        let limiter = RateLimiter::new(&[RateLimitConfig {
+            route: String::from("/{product}/login"),
+            per_ip: None,
+            per_account: Some(limit(1, 1)),
+            account_field: String::from("account"),
+            sdk_response: true,
+        }]);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(limiter))
+                .wrap(middleware::from_fn(rate_limit_middleware))
+                .service(login),
+        )
+        .await;
+        let request = |account: &str| {
+            TestRequest::post()
+                .uri("/hkrpg_global/login")
+                .set_json(serde_json::json!({ "account": account }))
+                .to_request()
+        };
+
+        let body: serde_json::Value =
+            read_body_json(call_service(&app, request("kiana")).await).await;
+        assert_eq!(body["account"], "kiana");
+        let response = call_service(&app, request("mei")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value =
+            read_body_json(call_service(&app, request("kiana")).await).await;
+        assert_eq!(body["retcode"], -1);
+    }
+}
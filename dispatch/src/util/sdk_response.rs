@@ -0,0 +1,189 @@
+use actix_web::HttpResponse;
+use serde::{Serialize, Serializer};
+
+// the retcodes the sdk gets from us. it only acts on a few of them, for the
+// rest it just shows the message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Retcode {
+    Ok,
+    // anything the sdk has no code for, like being rate limited
+    Fail,
+    WrongPassword,
+    InternalError,
+    BadRequest,
+    AccountBanned,
+    // missing account, or a token that's wrong or ran out
+    InvalidAccount,
+}
+
+impl Retcode {
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Ok => 0,
+            Self::Fail => -1,
+            Self::WrongPassword => -101,
+            Self::InternalError => 2,
+            Self::BadRequest => 5,
+            Self::AccountBanned => 1004,
+            Self::InvalidAccount => 1005,
+        }
+    }
+}
+
+impl Serialize for Retcode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+// `data` is `{}` for anything but a success
+#[derive(Serialize)]
+pub struct Empty {}
+
+// every `mdk/shield` and `combo/granter` reply is wrapped in this
+#[derive(Serialize)]
+pub struct SdkResponse<T> {
+    pub data: T,
+    pub message: String,
+    pub retcode: Retcode,
+}
+
+impl<T: Serialize> SdkResponse<T> {
+    pub fn ok(data: T) -> Self {
+        Self {
+            data,
+            message: String::from("OK"),
+            retcode: Retcode::Ok,
+        }
+    }
+}
+
+impl SdkResponse<Empty> {
+    pub fn error(retcode: Retcode, message: impl Into<String>) -> Self {
+        Self {
+            data: Empty {},
+            message: message.into(),
+            retcode,
+        }
+    }
+
+    pub fn internal_error() -> Self {
+        Self::error(Retcode::InternalError, "internal error")
+    }
+
+    pub fn bad_request() -> Self {
+        Self::error(Retcode::BadRequest, "bad request")
+    }
+}
+
+impl<T: Serialize> From<SdkResponse<T>> for HttpResponse {
+    fn from(response: SdkResponse<T>) -> Self {
+        HttpResponse::Ok().json(response)
+    }
+}
+
+// `mdk/shield/api/login` and `mdk/shield/api/verify`
+#[derive(Serialize)]
+pub struct ShieldLoginData {
+    pub account: ShieldAccount,
+    pub device_grant_required: bool,
+    pub reactivate_required: bool,
+    pub realperson_required: bool,
+    pub safe_mobile_required: bool,
+}
+
+#[derive(Serialize)]
+pub struct ShieldAccount {
+    pub area_code: String,
+    pub country: String,
+    pub email: String,
+    pub is_email_verify: String,
+    pub token: String,
+    pub uid: String,
+}
+
+impl ShieldLoginData {
+    pub fn new(uid: u32, username: &str, token: String) -> Self {
+        Self {
+            account: ShieldAccount {
+                area_code: String::from("**"),
+                country: String::from("ID"),
+                email: format!("{}@railgun.ps", username),
+                is_email_verify: String::from("1"),
+                token,
+                uid: uid.to_string(),
+            },
+            device_grant_required: false,
+            reactivate_required: false,
+            realperson_required: false,
+            safe_mobile_required: false,
+        }
+    }
+}
+
+// `combo/granter/login/v2/login`
+#[derive(Serialize)]
+pub struct GranterLoginData {
+    pub account_type: u32,
+    pub combo_id: String,
+    pub combo_token: String,
+    // a json object, sent as a string
+    pub data: String,
+    pub heartbeat: bool,
+    pub open_id: String,
+}
+
+impl GranterLoginData {
+    pub fn new(uid: u32, token: String) -> Self {
+        Self {
+            account_type: 1,
+            combo_id: uid.to_string(),
+            combo_token: token,
+            data: serde_json::json!({ "guest": false }).to_string(),
+            heartbeat: false,
+            open_id: uid.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_json<T: Serialize>(response: &SdkResponse<T>) -> String {
+        serde_json::to_string(response).unwrap()
+    }
+
+    #[test]
+    fn errors_have_empty_data() {
+        assert_eq!(
+            to_json(&SdkResponse::error(
+                Retcode::InvalidAccount,
+                "token mismatch"
+            )),
+            r#"{"data":{},"message":"token mismatch","retcode":1005}"#
+        );
+        assert_eq!(
+            to_json(&SdkResponse::internal_error()),
+            r#"{"data":{},"message":"internal error","retcode":2}"#
+        );
+    }
+
+    #[test]
+    fn shield_login_shape() {
+        let data = ShieldLoginData::new(1, "kia\"na", String::from("token"));
+        assert_eq!(
+            to_json(&SdkResponse::ok(data)),
+            r#"{"data":{"account":{"area_code":"**","country":"ID","email":"kia\"na@railgun.ps","is_email_verify":"1","token":"token","uid":"1"},"device_grant_required":false,"reactivate_required":false,"realperson_required":false,"safe_mobile_required":false},"message":"OK","retcode":0}"#
+        );
+    }
+
+    #[test]
+    fn granter_login_shape() {
+        let data = GranterLoginData::new(1, String::from("token"));
+        assert_eq!(
+            to_json(&SdkResponse::ok(data)),
+            r#"{"data":{"account_type":1,"combo_id":"1","combo_token":"token","data":"{\"guest\":false}","heartbeat":false,"open_id":"1"},"message":"OK","retcode":0}"#
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use super::fetch_srtools_data;
+use crate::net::session::Context;
+use common::proto::{GetAvatarDataCsReq, GetAvatarDataScRsp, Retcode};
+
+pub async fn on_get_avatar_data(ctx: Context, req: GetAvatarDataCsReq) -> GetAvatarDataScRsp {
+    let mut avatar_list = fetch_srtools_data(&ctx)
+        .await
+        .map(|v| v.avatar_list())
+        .unwrap_or_default();
+
+    if !req.is_get_all {
+        avatar_list.retain(|v| req.base_avatar_id_list.contains(&v.base_avatar_id));
+    }
+
+    GetAvatarDataScRsp {
+        retcode: Retcode::RetSucc as u32,
+        is_get_all: req.is_get_all,
+        avatar_list,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,101 @@
+use super::fetch_srtools_data;
+use crate::net::session::Context;
+use common::proto::{
+    BattleEventBattleInfo, BattleEventProperty, PveBattleResultCsReq, PveBattleResultScRsp,
+    Retcode, SceneBattleInfo, SceneCastSkillCsReq, SceneCastSkillScRsp, SpBarInfo,
+    StartCocoonStageCsReq, StartCocoonStageScRsp,
+};
+use database::srtools::SRToolsData;
+
+// only one battle runs per session, the client just echoes this back in the result
+const BATTLE_ID: u32 = 1;
+const WORLD_LEVEL: u32 = 6;
+const PATH_RESONANCE_FULL_SP: u32 = 10000;
+
+pub async fn on_start_cocoon_stage(
+    ctx: Context,
+    req: StartCocoonStageCsReq,
+) -> StartCocoonStageScRsp {
+    let Some(data) = fetch_srtools_data(&ctx).await else {
+        return StartCocoonStageScRsp {
+            retcode: Retcode::RetFail as u32,
+            ..Default::default()
+        };
+    };
+
+    StartCocoonStageScRsp {
+        retcode: Retcode::RetSucc as u32,
+        cocoon_id: req.cocoon_id,
+        prop_entity_id: req.prop_entity_id,
+        wave: req.wave,
+        battle_info: Some(build_battle_info(&data)),
+    }
+}
+
+// casting a technique on the overworld starts the synced battle if it hit something
+pub async fn on_scene_cast_skill(ctx: Context, req: SceneCastSkillCsReq) -> SceneCastSkillScRsp {
+    if req.hit_target_entity_id_list.is_empty() {
+        return SceneCastSkillScRsp {
+            retcode: Retcode::RetSucc as u32,
+            cast_entity_id: req.cast_entity_id,
+            ..Default::default()
+        };
+    }
+
+    let battle_info = fetch_srtools_data(&ctx)
+        .await
+        .map(|v| build_battle_info(&v));
+
+    SceneCastSkillScRsp {
+        retcode: Retcode::RetSucc as u32,
+        cast_entity_id: req.cast_entity_id,
+        battle_info,
+        ..Default::default()
+    }
+}
+
+pub async fn on_pve_battle_result(
+    _ctx: Context,
+    req: PveBattleResultCsReq,
+) -> PveBattleResultScRsp {
+    PveBattleResultScRsp {
+        retcode: Retcode::RetSucc as u32,
+        battle_id: req.battle_id,
+        stage_id: req.stage_id,
+        end_status: req.end_status,
+        check_identical: true,
+        ..Default::default()
+    }
+}
+
+fn build_battle_info(data: &SRToolsData) -> SceneBattleInfo {
+    let config = &data.battle_config;
+
+    let battle_event = if config.path_resonance_id != 0 {
+        vec![BattleEventBattleInfo {
+            battle_event_id: config.path_resonance_id,
+            status: Some(BattleEventProperty {
+                sp_bar: Some(SpBarInfo {
+                    cur_sp: PATH_RESONANCE_FULL_SP,
+                    max_sp: PATH_RESONANCE_FULL_SP,
+                }),
+            }),
+            ..Default::default()
+        }]
+    } else {
+        Vec::new()
+    };
+
+    SceneBattleInfo {
+        battle_id: BATTLE_ID,
+        stage_id: config.stage_id,
+        rounds_limit: config.cycle_count,
+        world_level: WORLD_LEVEL,
+        logic_random_seed: fastrand::u32(..),
+        battle_avatar_list: data.battle_avatar_list(),
+        monster_wave_list: config.monster_wave_list(),
+        buff_list: config.buff_list(),
+        battle_event,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,19 @@
+use super::fetch_srtools_data;
+use crate::net::session::Context;
+use common::proto::{GetBagCsReq, GetBagScRsp, Retcode};
+
+pub async fn on_get_bag(ctx: Context, _req: GetBagCsReq) -> GetBagScRsp {
+    let Some(data) = fetch_srtools_data(&ctx).await else {
+        return GetBagScRsp {
+            retcode: Retcode::RetSucc as u32,
+            ..Default::default()
+        };
+    };
+
+    GetBagScRsp {
+        retcode: Retcode::RetSucc as u32,
+        relic_list: data.relic_list(),
+        equipment_list: data.equipment_list(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,65 @@
+use super::fetch_srtools_data;
+use crate::net::session::Context;
+use common::proto::{
+    AvatarType, GetAllLineupDataCsReq, GetAllLineupDataScRsp, GetCurLineupDataCsReq,
+    GetCurLineupDataScRsp, LineupAvatar, LineupInfo, Retcode, SpBarInfo,
+};
+use database::srtools::SRToolsData;
+
+const LINEUP_MAX_MP: u32 = 5;
+const AVATAR_FULL_HP: u32 = 10000;
+
+pub async fn on_get_cur_lineup_data(
+    ctx: Context,
+    _req: GetCurLineupDataCsReq,
+) -> GetCurLineupDataScRsp {
+    let data = fetch_srtools_data(&ctx).await;
+
+    GetCurLineupDataScRsp {
+        retcode: Retcode::RetSucc as u32,
+        lineup: Some(build_lineup(data.as_ref())),
+    }
+}
+
+pub async fn on_get_all_lineup_data(
+    ctx: Context,
+    _req: GetAllLineupDataCsReq,
+) -> GetAllLineupDataScRsp {
+    let data = fetch_srtools_data(&ctx).await;
+
+    GetAllLineupDataScRsp {
+        retcode: Retcode::RetSucc as u32,
+        cur_index: 0,
+        lineup_list: vec![build_lineup(data.as_ref())],
+    }
+}
+
+fn build_lineup(data: Option<&SRToolsData>) -> LineupInfo {
+    let avatar_list = data
+        .map(|v| v.lineup_avatars())
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(slot, avatar)| LineupAvatar {
+            id: avatar.avatar_id,
+            slot: slot as u32,
+            hp: AVATAR_FULL_HP,
+            avatar_type: AvatarType::AvatarFormalType as i32,
+            sp_bar: Some(SpBarInfo {
+                cur_sp: avatar.sp_value,
+                max_sp: avatar.sp_max,
+            }),
+            ..Default::default()
+        })
+        .collect();
+
+    LineupInfo {
+        index: 0,
+        name: String::from("Lineup 1"),
+        leader_slot: 0,
+        mp: LINEUP_MAX_MP,
+        max_mp: LINEUP_MAX_MP,
+        avatar_list,
+        ..Default::default()
+    }
+}
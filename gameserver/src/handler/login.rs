@@ -0,0 +1,233 @@
+use crate::net::session::Context;
+use common::proto::{
+    BlackInfo, PlayerBasicInfo, PlayerGetTokenCsReq, PlayerGetTokenScRsp, PlayerLoginCsReq,
+    PlayerLoginFinishCsReq, PlayerLoginFinishScRsp, PlayerLoginScRsp, Retcode,
+};
+use common::time::{get_duration_since_unix, get_timezone_offset_hours};
+use common::token;
+use openssl::rand::rand_bytes;
+
+const BAN_TYPE_ACCOUNT: u32 = 1;
+
+pub async fn on_player_get_token(ctx: Context, req: PlayerGetTokenCsReq) -> PlayerGetTokenScRsp {
+    let Ok(uid) = req.account_uid.parse::<u32>() else {
+        return get_token_error(Retcode::RetAccountParaError, "bad request");
+    };
+    // forged and expired tokens are turned away before the database is asked
+    match token::verify(&req.token) {
+        Ok(claims) if claims.uid == uid => {}
+        Ok(_) => return get_token_error(Retcode::RetAccountVerifyError, "token mismatch"),
+        Err(e) => return get_token_error(Retcode::RetAccountVerifyError, &e.to_string()),
+    }
+
+    let account = match ctx.storage.accounts.fetch_by_uid(uid).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return get_token_error(Retcode::RetAccountVerifyError, "account doesn't exist");
+        }
+        Err(e) => {
+            tracing::error!("Fetching account by uid: {}", e);
+            return get_token_error(Retcode::RetServerInternalError, "internal error");
+        }
+    };
+
+    if account.token != req.token {
+        return get_token_error(Retcode::RetAccountVerifyError, "token mismatch");
+    }
+
+    if account.is_ban_active(get_duration_since_unix().as_secs() as i64) {
+        return PlayerGetTokenScRsp {
+            retcode: Retcode::RetInBlackList as u32,
+            msg: account.ban_reason.unwrap_or_default(),
+            uid: account.uid,
+            black_info: Some(BlackInfo {
+                begin_time: account.ban_start.unwrap_or_default(),
+                end_time: account.ban_end.unwrap_or(i64::MAX),
+                ban_type: BAN_TYPE_ACCOUNT,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+    }
+
+    // the seed decides the whole session key, so it has to be unguessable
+    let mut seed = [0; 8];
+    rand_bytes(&mut seed).expect("the openssl rng failed");
+    let secret_key_seed = u64::from_le_bytes(seed);
+    ctx.set_uid(account.uid);
+    ctx.set_key_seed(secret_key_seed);
+    tracing::info!("{} logged in as uid {}", ctx.addr, account.uid);
+
+    PlayerGetTokenScRsp {
+        retcode: Retcode::RetSucc as u32,
+        uid: account.uid,
+        secret_key_seed,
+        msg: String::from("OK"),
+        ..Default::default()
+    }
+}
+
+fn get_token_error(retcode: Retcode, msg: &str) -> PlayerGetTokenScRsp {
+    PlayerGetTokenScRsp {
+        retcode: retcode as u32,
+        msg: msg.to_string(),
+        ..Default::default()
+    }
+}
+
+pub async fn on_player_login(ctx: Context, req: PlayerLoginCsReq) -> PlayerLoginScRsp {
+    let Some(uid) = ctx.uid() else {
+        return PlayerLoginScRsp {
+            retcode: Retcode::RetAccountVerifyError as u32,
+            ..Default::default()
+        };
+    };
+
+    let account = match ctx.storage.accounts.fetch_by_uid(uid).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return PlayerLoginScRsp {
+                retcode: Retcode::RetAccountVerifyError as u32,
+                ..Default::default()
+            };
+        }
+        Err(e) => {
+            tracing::error!("Fetching account by uid: {}", e);
+            return PlayerLoginScRsp {
+                retcode: Retcode::RetServerInternalError as u32,
+                ..Default::default()
+            };
+        }
+    };
+
+    PlayerLoginScRsp {
+        retcode: Retcode::RetSucc as u32,
+        login_random: req.login_random,
+        server_timestamp_ms: get_duration_since_unix().as_millis() as u64,
+        cur_timezone: get_timezone_offset_hours(),
+        stamina: 240,
+        basic_info: Some(PlayerBasicInfo {
+            nickname: account.username,
+            level: 70,
+            world_level: 6,
+            stamina: 240,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub async fn on_player_login_finish(
+    _ctx: Context,
+    _req: PlayerLoginFinishCsReq,
+) -> PlayerLoginFinishScRsp {
+    PlayerLoginFinishScRsp {
+        retcode: Retcode::RetSucc as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::Storage;
+    use database::account::AccountDoc;
+    use database::repo::memory::MemoryStorage;
+
+    const UID: u32 = 10001;
+
+    async fn ctx_with_account(ban_end: Option<Option<i64>>) -> (Context, String) {
+        token::set_secret(b"test secret".to_vec());
+        let token = token::generate(UID);
+        let mut account = AccountDoc::new(UID, "kiana", String::new(), token.clone());
+        if let Some(end) = ban_end {
+            let now = get_duration_since_unix().as_secs() as i64;
+            account.ban("cheating", "admin", now, end);
+        }
+
+        let storage = Storage::new(MemoryStorage::default());
+        storage.accounts.register(&account).await.unwrap();
+        let ctx = Context::new("127.0.0.1:1".parse().unwrap(), storage);
+        (ctx, token)
+    }
+
+    fn get_token_req(uid: u32, token: &str) -> PlayerGetTokenCsReq {
+        PlayerGetTokenCsReq {
+            account_uid: uid.to_string(),
+            token: token.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn get_token_logs_the_session_in() {
+        let (ctx, token) = ctx_with_account(None).await;
+
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID, &token)).await;
+        assert_eq!(rsp.retcode, Retcode::RetSucc as u32);
+        assert_eq!(rsp.uid, UID);
+        assert_eq!(ctx.uid(), Some(UID));
+    }
+
+    #[tokio::test]
+    async fn get_token_rejects_bad_tokens() {
+        let (ctx, token) = ctx_with_account(None).await;
+
+        let forged = format!("{}x", token);
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID, &forged)).await;
+        assert_eq!(rsp.retcode, Retcode::RetAccountVerifyError as u32);
+
+        // signed, but not the one the account was last given
+        let stale = token::generate(UID);
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID, &stale)).await;
+        assert_eq!(rsp.retcode, Retcode::RetAccountVerifyError as u32);
+
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID + 1, &token)).await;
+        assert_eq!(rsp.retcode, Retcode::RetAccountVerifyError as u32);
+
+        let mut req = get_token_req(UID, &token);
+        req.account_uid = String::from("kiana");
+        let rsp = on_player_get_token(ctx.clone(), req).await;
+        assert_eq!(rsp.retcode, Retcode::RetAccountParaError as u32);
+
+        assert_eq!(ctx.uid(), None);
+    }
+
+    #[tokio::test]
+    async fn get_token_turns_banned_accounts_away() {
+        let (ctx, token) = ctx_with_account(Some(None)).await;
+
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID, &token)).await;
+        assert_eq!(rsp.retcode, Retcode::RetInBlackList as u32);
+        assert_eq!(rsp.msg, "cheating");
+        let black_info = rsp.black_info.unwrap();
+        assert_eq!(black_info.ban_type, BAN_TYPE_ACCOUNT);
+        assert_eq!(black_info.end_time, i64::MAX);
+        assert_eq!(ctx.uid(), None);
+
+        // one that has run out doesn't count
+        let (ctx, token) = ctx_with_account(Some(Some(1))).await;
+        let rsp = on_player_get_token(ctx.clone(), get_token_req(UID, &token)).await;
+        assert_eq!(rsp.retcode, Retcode::RetSucc as u32);
+    }
+
+    #[tokio::test]
+    async fn login_needs_the_token_exchange_first() {
+        let (ctx, token) = ctx_with_account(None).await;
+
+        let rsp = on_player_login(ctx.clone(), PlayerLoginCsReq::default()).await;
+        assert_eq!(rsp.retcode, Retcode::RetAccountVerifyError as u32);
+
+        on_player_get_token(ctx.clone(), get_token_req(UID, &token)).await;
+        let req = PlayerLoginCsReq {
+            login_random: 42,
+            ..Default::default()
+        };
+        let rsp = on_player_login(ctx.clone(), req).await;
+        assert_eq!(rsp.retcode, Retcode::RetSucc as u32);
+        assert_eq!(rsp.login_random, 42);
+        assert_eq!(rsp.basic_info.unwrap().nickname, "kiana");
+
+        let rsp = on_player_login_finish(ctx, PlayerLoginFinishCsReq::default()).await;
+        assert_eq!(rsp.retcode, Retcode::RetSucc as u32);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::net::router::Router;
+use crate::net::session::Context;
+use common::proto::cmd;
+use database::srtools::SRToolsData;
+
+pub mod avatar;
+pub mod battle;
+pub mod item;
+pub mod lineup;
+pub mod login;
+pub mod player;
+
+pub fn build_router() -> Router {
+    let mut router = Router::default();
+
+    router
+        .register(cmd::PLAYER_GET_TOKEN_CS_REQ, login::on_player_get_token)
+        .register(cmd::PLAYER_LOGIN_CS_REQ, login::on_player_login)
+        .register(
+            cmd::PLAYER_LOGIN_FINISH_CS_REQ,
+            login::on_player_login_finish,
+        )
+        .register(cmd::PLAYER_HEART_BEAT_CS_REQ, player::on_player_heart_beat)
+        .register(cmd::GET_AVATAR_DATA_CS_REQ, avatar::on_get_avatar_data)
+        .register(cmd::GET_BAG_CS_REQ, item::on_get_bag)
+        .register(
+            cmd::GET_CUR_LINEUP_DATA_CS_REQ,
+            lineup::on_get_cur_lineup_data,
+        )
+        .register(
+            cmd::GET_ALL_LINEUP_DATA_CS_REQ,
+            lineup::on_get_all_lineup_data,
+        )
+        .register(
+            cmd::START_COCOON_STAGE_CS_REQ,
+            battle::on_start_cocoon_stage,
+        )
+        .register(cmd::SCENE_CAST_SKILL_CS_REQ, battle::on_scene_cast_skill)
+        .register(cmd::PVE_BATTLE_RESULT_CS_REQ, battle::on_pve_battle_result);
+
+    router
+}
+
+// a missing doc or an unsynced account both mean there's nothing to serve
+pub async fn fetch_srtools_data(ctx: &Context) -> Option<SRToolsData> {
+    let uid = ctx.uid()?;
+    match ctx.storage.srtools.fetch_by_uid(uid).await {
+        Ok(v) => v.and_then(|v| v.data),
+        Err(e) => {
+            tracing::error!("Fetching SRToolsDoc by uid: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use crate::net::session::Context;
+use common::proto::{PlayerHeartBeatCsReq, PlayerHeartBeatScRsp};
+use common::time::get_duration_since_unix;
+
+pub async fn on_player_heart_beat(
+    _ctx: Context,
+    req: PlayerHeartBeatCsReq,
+) -> PlayerHeartBeatScRsp {
+    PlayerHeartBeatScRsp {
+        client_time_ms: req.client_time_ms,
+        server_time_ms: get_duration_since_unix().as_millis() as u64,
+        retcode: 0,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,89 @@
+use common::packet::xor::XorKey;
+use database::Storage;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+mod handler;
+mod net;
+
+use net::session::Session;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::io::Result<()> {
+    common::init_tracing();
+    let config = match common::config::init() {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Loading config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if config.database.migrations_dry_run {
+        if let Err(e) = database::dry_run_migrations().await {
+            tracing::error!("Dry running migrations: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = common::token::init(Path::new(&config.certs.token_secret_file)) {
+        tracing::error!("Loading token secret: {}", e);
+        std::process::exit(1);
+    }
+
+    // `None` talks to clients in the clear, both before and after the login
+    let dispatch_key: Option<XorKey> = match config.gameserver.xor_enabled {
+        true => match XorKey::load(Path::new(&config.certs.dispatch_key_file)) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::error!("Loading {}: {}", config.certs.dispatch_key_file, e);
+                std::process::exit(1);
+            }
+        },
+        false => {
+            tracing::warn!("gameserver.xor_enabled is off, only patched clients can connect");
+            None
+        }
+    };
+
+    let storage: Storage = match database::new_storage().await {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Opening database: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let router = Arc::new(handler::build_router());
+    let bind_target = config.gameserver_bind_target();
+    let listener = TcpListener::bind(bind_target).await?;
+    tracing::info!(
+        "gameserver listening on {}:{}",
+        bind_target.0,
+        bind_target.1
+    );
+
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!("Accepting connection: {}", e);
+                continue;
+            }
+        };
+
+        tracing::info!("new connection from {}", addr);
+        let router = router.clone();
+        let storage = storage.clone();
+        let dispatch_key = dispatch_key.clone();
+
+        tokio::spawn(async move {
+            let mut session = Session::new(stream, addr, router, storage, dispatch_key);
+            if let Err(e) = session.run().await {
+                tracing::error!("Session {}: {}", addr, e);
+            }
+            tracing::info!("{} disconnected", addr);
+        });
+    }
+}
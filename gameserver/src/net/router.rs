@@ -0,0 +1,203 @@
+use super::session::Context;
+use common::packet::head::PacketHead;
+use common::packet::hsr::NetPacket;
+use common::proto::Retcode;
+use common::proto::cmd::{self, CmdKind};
+use common::proto::prost::{DecodeError, Message, encoding};
+use std::collections::HashMap;
+use std::pin::Pin;
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Vec<u8>> + Send>>;
+type ErasedHandler =
+    Box<dyn Fn(Context, &[u8]) -> Result<HandlerFuture, DecodeError> + Send + Sync>;
+
+struct Route {
+    rsp_cmd: u16,
+    handler: ErasedHandler,
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<u16, Route>,
+}
+
+impl Router {
+    pub fn register<Req, Rsp, F, Fut>(&mut self, req_cmd: u16, handler: F) -> &mut Self
+    where
+        Req: Message + Default + 'static,
+        Rsp: Message + 'static,
+        F: Fn(Context, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Rsp> + Send + 'static,
+    {
+        let rsp_cmd = cmd::response_cmd_for(req_cmd)
+            .unwrap_or_else(|| panic!("{} has no paired response", cmd_display(req_cmd)));
+
+        let handler: ErasedHandler = Box::new(move |ctx, body| {
+            let req = Req::decode(body)?;
+            let fut = handler(ctx, req);
+            Ok(Box::pin(async move { fut.await.encode_to_vec() }))
+        });
+
+        if self
+            .routes
+            .insert(req_cmd, Route { rsp_cmd, handler })
+            .is_some()
+        {
+            tracing::warn!("handler for {} registered twice", cmd_display(req_cmd));
+        }
+
+        self
+    }
+
+    pub async fn handle(
+        &self,
+        ctx: Context,
+        packet: &NetPacket,
+        rsp_head: &PacketHead,
+    ) -> Option<NetPacket> {
+        let Some(route) = self.routes.get(&packet.cmd) else {
+            if cmd::cmd_kind(packet.cmd) != Some(CmdKind::Request) {
+                tracing::warn!(
+                    "{} sent {}, not a request",
+                    ctx.addr,
+                    cmd_display(packet.cmd)
+                );
+                return None;
+            }
+            tracing::warn!("{} sent unhandled {}", ctx.addr, cmd_display(packet.cmd));
+            return Self::error_response(packet.cmd, rsp_head);
+        };
+
+        let fut = match (route.handler)(ctx, &packet.body) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!("Decoding {}: {}", cmd_display(packet.cmd), e);
+                return Self::error_response(packet.cmd, rsp_head);
+            }
+        };
+
+        Some(NetPacket {
+            cmd: route.rsp_cmd,
+            head: rsp_head.encode_to_vec(),
+            body: fut.await,
+        })
+    }
+
+    // only the retcode is set, an empty body would decode as retcode 0 and
+    // the client would take the request as done. responses without one stay empty
+    fn error_response(req_cmd: u16, rsp_head: &PacketHead) -> Option<NetPacket> {
+        let rsp_cmd = cmd::response_cmd_for(req_cmd)?;
+        let mut body = Vec::new();
+        if let Some(tag) = cmd::retcode_tag_for(rsp_cmd) {
+            encoding::uint32::encode(tag, &(Retcode::RetFail as u32), &mut body);
+        }
+
+        Some(NetPacket {
+            cmd: rsp_cmd,
+            head: rsp_head.encode_to_vec(),
+            body,
+        })
+    }
+}
+
+pub fn cmd_display(id: u16) -> String {
+    format!("{} ({})", cmd::cmd_name(id).unwrap_or("UnknownCmd"), id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::proto::{GetBagScRsp, PlayerHeartBeatCsReq, PlayerHeartBeatScRsp};
+    use database::Storage;
+    use database::repo::memory::MemoryStorage;
+
+    fn ctx() -> Context {
+        Context::new(
+            "127.0.0.1:1".parse().unwrap(),
+            Storage::new(MemoryStorage::default()),
+        )
+    }
+
+    fn rsp_head() -> PacketHead {
+        PacketHead {
+            packet_id: 3,
+            client_sequence_id: 7,
+            ..Default::default()
+        }
+    }
+
+    fn request(cmd: u16, body: Vec<u8>) -> NetPacket {
+        NetPacket {
+            cmd,
+            head: Vec::new(),
+            body,
+        }
+    }
+
+    fn heart_beat_router() -> Router {
+        let mut router = Router::default();
+        router.register(
+            cmd::PLAYER_HEART_BEAT_CS_REQ,
+            |_ctx, req: PlayerHeartBeatCsReq| async move {
+                PlayerHeartBeatScRsp {
+                    client_time_ms: req.client_time_ms,
+                    ..Default::default()
+                }
+            },
+        );
+        router
+    }
+
+    #[tokio::test]
+    async fn dispatches_to_the_registered_handler() {
+        let req = PlayerHeartBeatCsReq {
+            client_time_ms: 1234,
+            ..Default::default()
+        };
+        let packet = request(cmd::PLAYER_HEART_BEAT_CS_REQ, req.encode_to_vec());
+
+        let rsp = heart_beat_router()
+            .handle(ctx(), &packet, &rsp_head())
+            .await
+            .unwrap();
+        assert_eq!(rsp.cmd, cmd::PLAYER_HEART_BEAT_SC_RSP);
+        assert_eq!(rsp.decode_head().unwrap(), rsp_head());
+        let body = PlayerHeartBeatScRsp::decode(rsp.body.as_slice()).unwrap();
+        assert_eq!(body.client_time_ms, 1234);
+    }
+
+    #[tokio::test]
+    async fn unhandled_and_undecodable_requests_still_get_their_response() {
+        let router = heart_beat_router();
+
+        let unhandled = request(cmd::GET_BAG_CS_REQ, Vec::new());
+        let rsp = router.handle(ctx(), &unhandled, &rsp_head()).await.unwrap();
+        assert_eq!(rsp.cmd, cmd::GET_BAG_SC_RSP);
+        assert_eq!(rsp.decode_head().unwrap(), rsp_head());
+        let body = GetBagScRsp::decode(rsp.body.as_slice()).unwrap();
+        assert_eq!(body.retcode, Retcode::RetFail as u32);
+
+        // a length-delimited field running past the end of the body
+        let undecodable = request(cmd::PLAYER_HEART_BEAT_CS_REQ, vec![0x3A, 0x10]);
+        let rsp = router
+            .handle(ctx(), &undecodable, &rsp_head())
+            .await
+            .unwrap();
+        assert_eq!(rsp.cmd, cmd::PLAYER_HEART_BEAT_SC_RSP);
+        let body = PlayerHeartBeatScRsp::decode(rsp.body.as_slice()).unwrap();
+        assert_eq!(body.retcode, Retcode::RetFail as u32);
+    }
+
+    #[tokio::test]
+    async fn cmds_without_a_response_get_no_reply() {
+        let router = heart_beat_router();
+        for cmd in [
+            u16::MAX,
+            cmd::PLAYER_GET_TOKEN_SC_RSP,
+            cmd::PLAYER_KICK_OUT_SC_NOTIFY,
+        ] {
+            let packet = request(cmd, Vec::new());
+            assert!(router.handle(ctx(), &packet, &rsp_head()).await.is_none());
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use super::router::{Router, cmd_display};
+use common::packet::codec::NetPacketCodec;
+use common::packet::head::PacketHead;
+use common::packet::hsr::NetPacket;
+use common::packet::xor::XorKey;
+use common::proto::cmd;
+use common::time::get_duration_since_unix;
+use database::Storage;
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
+
+#[derive(Default)]
+struct SessionState {
+    uid: Option<u32>,
+    key_seed: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct Context {
+    pub addr: SocketAddr,
+    pub storage: Storage,
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl Context {
+    pub fn new(addr: SocketAddr, storage: Storage) -> Self {
+        Self {
+            addr,
+            storage,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn uid(&self) -> Option<u32> {
+        self.state.lock().unwrap().uid
+    }
+
+    pub fn set_uid(&self, uid: u32) {
+        self.state.lock().unwrap().uid = Some(uid);
+    }
+
+    // the session switches to the derived key once the current response is sent
+    pub fn set_key_seed(&self, seed: u64) {
+        self.state.lock().unwrap().key_seed = Some(seed);
+    }
+
+    fn take_key_seed(&self) -> Option<u64> {
+        self.state.lock().unwrap().key_seed.take()
+    }
+}
+
+pub struct Session {
+    framed: Framed<TcpStream, NetPacketCodec>,
+    router: Arc<Router>,
+    ctx: Context,
+    xor_enabled: bool,
+    sequence: Sequence,
+}
+
+// numbers the packets the server sends and follows the ones the client sends
+struct Sequence {
+    next_packet_id: u32,
+    last_client_sequence_id: u32,
+}
+
+impl Session {
+    // `dispatch_key` is what the client encrypts with until it has logged in,
+    // `None` leaves the whole session unencrypted
+    pub fn new(
+        stream: TcpStream,
+        addr: SocketAddr,
+        router: Arc<Router>,
+        storage: Storage,
+        dispatch_key: Option<XorKey>,
+    ) -> Self {
+        let xor_enabled = dispatch_key.is_some();
+        let codec = match dispatch_key {
+            Some(key) => NetPacketCodec::default().with_key(key),
+            None => NetPacketCodec::default(),
+        };
+
+        Self {
+            framed: Framed::new(stream, codec),
+            router,
+            ctx: Context::new(addr, storage),
+            xor_enabled,
+            sequence: Sequence::new(),
+        }
+    }
+
+    pub async fn run(&mut self) -> std::io::Result<()> {
+        loop {
+            let packet = match self.framed.next().await {
+                Some(v) => v?,
+                None => return Ok(()),
+            };
+
+            // everything but the token exchange requires a verified account
+            if self.ctx.uid().is_none() && packet.cmd != cmd::PLAYER_GET_TOKEN_CS_REQ {
+                tracing::warn!(
+                    "{} sent {} before logging in",
+                    self.ctx.addr,
+                    cmd_display(packet.cmd)
+                );
+                return Ok(());
+            }
+
+            let req_head = request_head(&self.ctx, &packet);
+            let last_client_sequence_id = self.sequence.last_client_sequence_id;
+            if !self.sequence.track_client(req_head.client_sequence_id) {
+                tracing::warn!(
+                    "{} sent sequence {} after {}",
+                    self.ctx.addr,
+                    req_head.client_sequence_id,
+                    last_client_sequence_id
+                );
+            }
+
+            let rsp_head = self.sequence.next_head(
+                req_head.client_sequence_id,
+                self.ctx.uid().unwrap_or_default(),
+            );
+            let rsp = self
+                .router
+                .handle(self.ctx.clone(), &packet, &rsp_head)
+                .await;
+            if let Some(rsp) = rsp {
+                self.send(rsp).await?;
+            }
+
+            if let Some(seed) = self.ctx.take_key_seed()
+                && self.xor_enabled
+            {
+                self.framed.codec_mut().set_key(XorKey::from_seed(seed));
+            }
+        }
+    }
+
+    pub async fn send(&mut self, packet: NetPacket) -> std::io::Result<()> {
+        self.framed.send(packet).await
+    }
+}
+
+// a head that doesn't decode is treated as empty, the packet is still handled
+fn request_head(ctx: &Context, packet: &NetPacket) -> PacketHead {
+    match packet.decode_head() {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!(
+                "{} sent {} with a bad head: {}",
+                ctx.addr,
+                cmd_display(packet.cmd),
+                e
+            );
+            PacketHead::default()
+        }
+    }
+}
+
+impl Sequence {
+    fn new() -> Self {
+        Self {
+            next_packet_id: 1,
+            last_client_sequence_id: 0,
+        }
+    }
+
+    fn next_head(&mut self, client_sequence_id: u32, user_id: u32) -> PacketHead {
+        let packet_id = self.next_packet_id;
+        self.next_packet_id = self.next_packet_id.wrapping_add(1);
+
+        PacketHead {
+            packet_id,
+            client_sequence_id,
+            sent_ms: get_duration_since_unix().as_millis() as u64,
+            user_id,
+            ..Default::default()
+        }
+    }
+
+    // false when the client went backwards or repeated itself, the packet is
+    // still answered. 0 means the client didn't number the packet
+    fn track_client(&mut self, client_sequence_id: u32) -> bool {
+        if client_sequence_id == 0 {
+            return true;
+        }
+
+        if client_sequence_id <= self.last_client_sequence_id {
+            return false;
+        }
+
+        self.last_client_sequence_id = client_sequence_id;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::proto::prost::Message;
+    use database::repo::memory::MemoryStorage;
+
+    #[test]
+    fn packet_ids_count_up_and_echo_the_client_sequence() {
+        let mut sequence = Sequence::new();
+
+        let first = sequence.next_head(7, 10001);
+        let second = sequence.next_head(8, 10001);
+        assert_eq!(first.packet_id, 1);
+        assert_eq!(second.packet_id, 2);
+        assert_eq!(first.client_sequence_id, 7);
+        assert_eq!(second.client_sequence_id, 8);
+        assert_eq!(second.user_id, 10001);
+
+        sequence.next_packet_id = u32::MAX;
+        assert_eq!(sequence.next_head(0, 0).packet_id, u32::MAX);
+        assert_eq!(sequence.next_head(0, 0).packet_id, 0);
+    }
+
+    #[test]
+    fn client_sequence_only_moves_forward() {
+        let mut sequence = Sequence::new();
+
+        assert!(sequence.track_client(1));
+        assert!(sequence.track_client(5));
+        assert!(!sequence.track_client(5));
+        assert!(!sequence.track_client(3));
+        assert_eq!(sequence.last_client_sequence_id, 5);
+
+        // unnumbered packets are always fine and don't reset it
+        assert!(sequence.track_client(0));
+        assert_eq!(sequence.last_client_sequence_id, 5);
+    }
+
+    #[test]
+    fn bad_head_falls_back_to_the_default() {
+        let ctx = Context::new(
+            "127.0.0.1:1".parse().unwrap(),
+            Storage::new(MemoryStorage::default()),
+        );
+        let head = PacketHead {
+            client_sequence_id: 3,
+            ..Default::default()
+        };
+        let mut packet = NetPacket {
+            cmd: cmd::PLAYER_HEART_BEAT_CS_REQ,
+            head: head.encode_to_vec(),
+            body: Vec::new(),
+        };
+        assert_eq!(request_head(&ctx, &packet), head);
+
+        // a length-delimited field running past the end of the head
+        packet.head = vec![0x3A, 0x10];
+        assert_eq!(request_head(&ctx, &packet), PacketHead::default());
+    }
+}